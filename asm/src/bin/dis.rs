@@ -0,0 +1,88 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::process;
+
+use asm::{Disassembler, Encoding, Object};
+use clap::{Parser, ValueHint};
+use env_logger as logger;
+use log::error;
+
+fn main() {
+    // Initialize logger
+    logger::Builder::new()
+        .default_format()
+        .format_indent(Some(12))
+        .format_timestamp(None)
+        .parse_default_env()
+        .init();
+    // Parse opts
+    let args = Args::parse();
+
+    // Instantiate a disassembler
+    let mut d = Disassembler::new();
+    // Select the instruction encoding
+    if args.legacy {
+        d.encoding(Encoding::Legacy);
+    }
+    // Read each object naming symbols
+    for path in &args.symbols {
+        let obj = File::open(path)
+            .map_err(Into::into)
+            .and_then(|f| Object::read(&mut BufReader::new(f)))
+            .unwrap_or_else(|err| {
+                error!("{}: `{}`", err, path.display());
+                process::exit(1);
+            });
+        d.add(obj);
+    }
+    // Read input image
+    let image = fs::read(&args.image).unwrap_or_else(|err| {
+        error!("{}: `{}`", err, args.image.display());
+        process::exit(1);
+    });
+    // Disassemble the image
+    let text = d.dis(&image).unwrap_or_else(|err| {
+        error!("{}", err);
+        process::exit(1);
+    });
+    // Write output file
+    match &args.out {
+        Some(out) => fs::write(out, text).unwrap_or_else(|err| {
+            error!("{}: `{}`", err, out.display());
+            process::exit(1);
+        }),
+        None => print!("{}", text),
+    }
+}
+
+/// Disassembler for the KAP-16 processor.
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+struct Args {
+    /// Input binary file
+    #[clap(parse(from_os_str))]
+    #[clap(value_hint = ValueHint::FilePath)]
+    image: PathBuf,
+
+    /// Output source file [default: stdout]
+    #[clap(short, long)]
+    #[clap(parse(from_os_str))]
+    #[clap(value_hint = ValueHint::FilePath)]
+    out: Option<PathBuf>,
+
+    /// Object file the image was linked from, in link order, naming symbols
+    #[clap(short, long)]
+    #[clap(parse(from_os_str))]
+    #[clap(value_hint = ValueHint::FilePath)]
+    symbols: Vec<PathBuf>,
+
+    /// Decode using the legacy fixed-width opcodes
+    #[clap(long)]
+    legacy: bool,
+
+    /// Use verbose output (-v, -vv, -vvv, etc.)
+    #[clap(short, long)]
+    #[clap(parse(from_occurrences))]
+    verbose: u8,
+}
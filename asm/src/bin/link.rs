@@ -0,0 +1,68 @@
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::process;
+
+use asm::{Linker, Object};
+use clap::{Parser, ValueHint};
+use env_logger as logger;
+use log::error;
+
+fn main() {
+    // Initialize logger
+    logger::Builder::new()
+        .default_format()
+        .format_indent(Some(12))
+        .format_timestamp(None)
+        .parse_default_env()
+        .init();
+    // Parse opts
+    let args = Args::parse();
+
+    // Instantiate a linker
+    let mut l = Linker::new();
+    // Read each input object
+    for path in &args.objs {
+        let obj = File::open(path)
+            .map_err(Into::into)
+            .and_then(|f| Object::read(&mut BufReader::new(f)))
+            .unwrap_or_else(|err| {
+                error!("{}: `{}`", err, path.display());
+                process::exit(1);
+            });
+        l.add(obj);
+    }
+    // Link objects into an image
+    let bytes = l.link().unwrap_or_else(|err| {
+        error!("{}", err);
+        process::exit(1);
+    });
+    // Write output file
+    fs::write(&args.out, bytes).unwrap_or_else(|err| {
+        error!("{}: `{}`", err, &args.out.display());
+        process::exit(1);
+    });
+}
+
+/// Linker for the KAP-16 processor.
+#[derive(Debug, Parser)]
+#[clap(author, version, about)]
+struct Args {
+    /// Input object file
+    #[clap(parse(from_os_str))]
+    #[clap(min_values = 1)]
+    #[clap(value_hint = ValueHint::FilePath)]
+    objs: Vec<PathBuf>,
+
+    /// Output binary file
+    #[clap(short, long)]
+    #[clap(parse(from_os_str))]
+    #[clap(default_value = "a.out")]
+    #[clap(value_hint = ValueHint::FilePath)]
+    out: PathBuf,
+
+    /// Use verbose output (-v, -vv, -vvv, etc.)
+    #[clap(short, long)]
+    #[clap(parse(from_occurrences))]
+    verbose: u8,
+}
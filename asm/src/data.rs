@@ -0,0 +1,217 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use isa::lex;
+
+use crate::expr;
+use crate::line::Line;
+use crate::pool;
+use crate::{uarch, VerboseError, WORDSIZE};
+
+/// Data directives, emitting bytes into the output image.
+///
+/// | Directive              | Operation                                        |
+/// | ---------------------- | ------------------------------------------------ |
+/// | `.word EXPR, ..`       | Emit each expression as a little-endian word     |
+/// | `.byte EXPR, ..`       | Emit each expression as a byte                   |
+/// | `.string "text"`       | Emit the bytes of a string, followed by a `NUL`  |
+/// | `.space SIZE [, BYTE]` | Reserve `SIZE` bytes, filled with `BYTE` or zero |
+/// | `.align [SIZE]`        | Pad with zeros to a multiple of `SIZE` bytes     |
+/// | `.pool`, `.ltorg`      | Place pending literals (see [`pool::Pool`])      |
+///
+/// Within data, symbols evaluate to their absolute address.
+pub fn parse(mut line: Line) -> Result<Line, VerboseError> {
+    let tokens: Vec<&str> = line.tokens.iter().map(String::as_str).collect();
+    // Layout must be known before symbols are resolved, so is evaluated now
+    let args = match tokens[..] {
+        [".", "word" | "byte", _, ..] => return Ok(line),
+        [".", "string", text] => {
+            lex::parse_str(text).map_err(|err| VerboseError::new(err.into(), &line))?;
+            return Ok(line);
+        }
+        [".", "space", _, ..] => {
+            let args = constants(&line)?;
+            if !(0..1 << uarch::BITS).contains(&args[0]) {
+                return Err(DataError::SpaceRange.at(&line));
+            }
+            match args[..] {
+                [_] => args,
+                [_, fill] if byte(fill).is_some() => args,
+                _ => return Err(DataError::FillRange.at(&line)),
+            }
+        }
+        [".", "align"] => vec![WORDSIZE as i32],
+        [".", "pool" | "ltorg"] => {
+            line.tokens[1] = "pool".to_string();
+            return Ok(line);
+        }
+        [".", "align", _, ..] => {
+            let args = constants(&line)?;
+            match args[..] {
+                [align] if align > 0 && (align as uarch).is_power_of_two() => args,
+                _ => return Err(DataError::Alignment.at(&line)),
+            }
+        }
+        _ => return Err(DataError::Malformed.at(&line)),
+    };
+    // Replace arguments with their evaluated values
+    let args = args
+        .iter()
+        .map(|&arg| expr::imm(arg).map_err(|err| VerboseError::new(err.into(), &line)))
+        .collect::<Result<Vec<_>, _>>()?;
+    line.tokens.truncate(2);
+    for (i, arg) in args.into_iter().enumerate() {
+        if i > 0 {
+            line.tokens.push(",".to_string());
+        }
+        line.tokens.push(arg);
+    }
+    Ok(line)
+}
+
+/// Size in bytes of a parsed line, placed at `addr`.
+pub fn size(tokens: &[String], addr: usize) -> usize {
+    let args = || tokens[2..].split(|token| token == ",");
+    match tokens.get(..2) {
+        Some([dot, directive]) if dot == "." => match directive.as_str() {
+            "word" => WORDSIZE * args().count(),
+            "byte" => args().count(),
+            "string" => lex::parse_str(&tokens[2]).map_or(0, |text| text.len() + 1),
+            "space" => imm(&tokens[2]),
+            "pool" => pool::size(tokens, addr),
+            "align" => addr.next_multiple_of(imm(&tokens[2])) - addr,
+            _ => 0,
+        },
+        _ => WORDSIZE,
+    }
+}
+
+/// Emits the bytes of a data directive placed at `addr`.
+pub fn emit(tokens: &[String], addr: usize) -> Result<Vec<u8>, Box<dyn Error>> {
+    let args = tokens[2..].split(|token| token == ",");
+    Ok(match tokens[1].as_str() {
+        "word" => args
+            .map(|arg| match arg {
+                [arg] => Ok(lex::parse_imm(arg)?.to_le_bytes()),
+                _ => Err(DataError::Malformed.into()),
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?
+            .concat(),
+        "byte" => args
+            .map(|arg| match arg {
+                [arg] => byte(lex::parse_imm(arg)? as i32).ok_or(DataError::ByteRange.into()),
+                _ => Err(DataError::Malformed.into()),
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?,
+        "string" => [lex::parse_str(&tokens[2])?, vec![0]].concat(),
+        "space" => match tokens[3..] {
+            [_, ref fill] => vec![lex::parse_imm(fill)? as u8; imm(&tokens[2])],
+            _ => vec![0; imm(&tokens[2])],
+        },
+        _ => vec![0; size(tokens, addr)],
+    })
+}
+
+fn constants(line: &Line) -> Result<Vec<i32>, VerboseError> {
+    line.tokens[2..]
+        .split(|token| token == ",")
+        .map(|arg| expr::eval(arg, |_| None))
+        .collect::<Result<_, _>>()
+        .map_err(|err| VerboseError::new(err.into(), line))
+}
+
+fn imm(token: &str) -> usize {
+    lex::parse_imm(token).map_or(0, usize::from)
+}
+
+fn byte(value: i32) -> Option<u8> {
+    match value {
+        -0x80..=0xff => Some(value as u8),
+        0xff80..=0xffff => Some(value as u8),
+        _ => None,
+    }
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Malformed,
+    ByteRange,
+    FillRange,
+    SpaceRange,
+    Alignment,
+}
+
+impl DataError {
+    fn at(self, line: &Line) -> VerboseError {
+        VerboseError::new(self.into(), line)
+    }
+}
+
+impl Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Malformed => "Unknown or malformed directive",
+                Self::ByteRange => "Value does not fit in a byte",
+                Self::FillRange => "Expected a single byte to fill with",
+                Self::SpaceRange => "Size must be non-negative and fit in the address space",
+                Self::Alignment => "Alignment must be a power of two",
+            }
+        )
+    }
+}
+
+impl Error for DataError {}
+
+#[cfg(test)]
+mod tests {
+    use isa::Encoding;
+
+    use super::*;
+    use crate::test_util::lines;
+    use crate::unit::Unit;
+
+    fn asm(src: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let (obj, _) = Unit::new(lines("test.s", src))?
+            .asm(Encoding::Huffman, false)
+            .map_err(|mut errs| errs.remove(0))?;
+        Ok(obj.sections[0].data.clone())
+    }
+
+    #[test]
+    fn directives() {
+        let src = r#"
+            start:
+                ldr r0, value
+                hlt
+            msg:
+                .string "hi\n"
+                .byte 'a', -0x1
+                .align
+            value:
+                .word msg, end - start
+                .space 0d3, 0xaa
+                .align 0d4
+            end:
+        "#;
+        assert_eq!(
+            asm(src).unwrap(),
+            [
+                0x84, 0x30, 0x00, 0x0c, b'h', b'i', b'\n', 0x00, b'a', 0xff, 0x04, 0x00, 0x14,
+                0x00, 0xaa, 0xaa, 0xaa, 0x00, 0x00, 0x00,
+            ]
+        );
+        // Malformed directives are rejected
+        assert!(asm(".byte 0x100").is_err());
+        assert!(asm(".align 0d3").is_err());
+        assert!(asm(".space end").is_err());
+        assert!(asm(".space -0x1").is_err());
+        assert!(asm(".space 0x10000").is_err());
+        assert!(asm(".string \"\\q\"").is_err());
+        assert!(asm(".bogus").is_err());
+        // Instructions must remain aligned
+        assert!(asm(".byte 0x0\nhlt").is_err());
+    }
+}
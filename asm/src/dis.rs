@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display, Write};
+use std::ops::Range;
+
+use isa::inst::{bra::Bra, ldr::Ldr, str::Str};
+use isa::{iarch, lex, uarch, Encoding, Instruction, Op2, WORDSIZE};
+
+use crate::link;
+use crate::obj::Object;
+
+/// Disassembler, recovering assembly source from a linked memory image.
+///
+/// Each word that decodes to an instruction encoding back to the same word
+/// is listed as that instruction, with PC-relative targets within the image
+/// named by label. All other words, along with the literal pools of added
+/// objects, are kept as `.word` data, such that the listing re-assembles and
+/// links to an identical image. Legacy images are decoded from the start of
+/// memory, where they begin execution, rather than following a vector table.
+#[derive(Debug, Default)]
+pub struct Disassembler {
+    enc: Encoding,
+    objs: Vec<Object>,
+}
+
+/// Decoded contents of an image.
+enum Item {
+    Instr(Instruction, uarch),
+    Word(uarch),
+    Byte(u8),
+}
+
+impl Disassembler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn encoding(&mut self, enc: Encoding) {
+        self.enc = enc;
+    }
+
+    /// Adds an object the image was linked from, naming labels after its
+    /// symbols and keeping its pools as data.
+    pub fn add(&mut self, obj: Object) {
+        self.objs.push(obj);
+    }
+
+    pub fn dis(&self, image: &[u8]) -> Result<String, DisError> {
+        // Read the reset vector, if any
+        let base = link::base(self.enc);
+        let entry = match self.enc {
+            Encoding::Huffman => {
+                let vector = image.get(..WORDSIZE).ok_or(DisError::Truncated)?;
+                uarch::from_le_bytes([vector[0], vector[1]]) as usize
+            }
+            Encoding::Legacy => base,
+        };
+        // Decode each word following the vector table, keeping pools as data
+        let pools = self.pools();
+        let mut items = Vec::new();
+        let mut addr = base;
+        while addr < image.len() {
+            let pooled = pools.iter().any(|pool| pool.contains(&addr));
+            let item = match image[addr..] {
+                [lo, hi, ..] if pooled => Item::Word(uarch::from_le_bytes([lo, hi])),
+                [lo, hi, ..] => self.decode(uarch::from_le_bytes([lo, hi])),
+                [byte, ..] => Item::Byte(byte),
+                [] => unreachable!(),
+            };
+            items.push((addr, item));
+            addr = (addr + WORDSIZE).min(image.len());
+        }
+        // Label each target within the image
+        let within =
+            |addr: usize| (base..=image.len()).contains(&addr) && addr.is_multiple_of(WORDSIZE);
+        let mut labels = self.symbols();
+        labels.retain(|&addr, _| within(addr));
+        for (addr, item) in &items {
+            if let Some(target) = target(*addr, item).filter(|&target| within(target)) {
+                labels
+                    .entry(target)
+                    .or_insert_with(|| format!("L{:04x}", target));
+            }
+        }
+        // List each item, preceded by the entry and labels at its address
+        let mut out = String::new();
+        let enc = format!("{:?}", self.enc).to_lowercase();
+        writeln!(out, "; Disassembled from a {} encoded image", enc).unwrap();
+        if self.enc == Encoding::Legacy {
+            writeln!(out, "; Re-assemble using `--legacy`").unwrap();
+        }
+        if !within(entry) {
+            writeln!(out, "; Reset vector {:#06x} lies outside the image", entry).unwrap();
+        }
+        for (addr, item) in items
+            .iter()
+            .map(|(addr, item)| (*addr, Some(item)))
+            .chain([(image.len(), None)])
+        {
+            if addr == entry {
+                writeln!(out, ".entry").unwrap();
+            }
+            if let Some(label) = labels.get(&addr) {
+                writeln!(out, "{}:", label).unwrap();
+            }
+            let (text, raw) = match item {
+                Some(Item::Instr(instr, word)) => {
+                    let text = render(instr, |imm| {
+                        let target = (addr + WORDSIZE).wrapping_add_signed(imm as iarch as isize);
+                        labels
+                            .get(&target)
+                            .cloned()
+                            .unwrap_or_else(|| format!("{:#06x}", imm))
+                    });
+                    (text, format!("{:04x}", word))
+                }
+                Some(Item::Word(word)) => (format!(".word {:#06x}", word), format!("{:04x}", word)),
+                Some(Item::Byte(byte)) => (format!(".byte {:#04x}", byte), format!("{:02x}", byte)),
+                None => continue,
+            };
+            writeln!(out, "    {:<24}; {:04x}: {}", text, addr, raw).unwrap();
+        }
+        Ok(out)
+    }
+
+    /// Decodes a word, keeping it as data unless it round-trips exactly.
+    fn decode(&self, word: uarch) -> Item {
+        let exact = Instruction::decode(word, self.enc).ok().filter(|instr| {
+            render(instr, |imm| format!("{:#06x}", imm))
+                .parse::<Instruction>()
+                .and_then(|instr| Ok(instr.encode(self.enc)?))
+                .is_ok_and(|encoded| encoded == word)
+        });
+        match exact {
+            Some(instr) => Item::Instr(instr, word),
+            None => Item::Word(word),
+        }
+    }
+
+    /// Finds the base address of each object's sections, placing them as the
+    /// linker does.
+    fn bases(&self) -> Vec<Vec<usize>> {
+        let mut addr = link::base(self.enc);
+        self.objs
+            .iter()
+            .map(|obj| {
+                obj.sections
+                    .iter()
+                    .map(|section| {
+                        addr = addr.next_multiple_of(WORDSIZE);
+                        let base = addr;
+                        addr += section.data.len();
+                        base
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Collects the address ranges of literal pools.
+    fn pools(&self) -> Vec<Range<usize>> {
+        let mut pools = Vec::new();
+        for (obj, bases) in self.objs.iter().zip(self.bases()) {
+            for pool in &obj.pools {
+                if let Some(base) = bases.get(pool.section) {
+                    pools.push(base + pool.offset..base + pool.offset + pool.size);
+                }
+            }
+        }
+        pools
+    }
+
+    /// Collects symbol names by address.
+    fn symbols(&self) -> BTreeMap<usize, String> {
+        let mut labels = BTreeMap::new();
+        let mut names = HashSet::new();
+        for (obj, bases) in self.objs.iter().zip(self.bases()) {
+            for symbol in &obj.symbols {
+                let Some(&base) = symbol.section.and_then(|idx| bases.get(idx)) else {
+                    continue;
+                };
+                // Local names may repeat across objects, but labels may not
+                if labels.contains_key(&(base + symbol.value)) || !names.insert(&symbol.name) {
+                    continue;
+                }
+                labels.insert(base + symbol.value, symbol.name.clone());
+            }
+        }
+        labels
+    }
+}
+
+/// Finds the address targeted by a PC-relative instruction at `addr`.
+fn target(addr: usize, item: &Item) -> Option<usize> {
+    let Item::Instr(instr, _) = item else {
+        return None;
+    };
+    match instr {
+        Instruction::Bra(Bra {
+            op2: Op2::Imm(imm), ..
+        })
+        | Instruction::Ldr(Ldr {
+            op2: Op2::Imm(imm), ..
+        })
+        | Instruction::Str(Str {
+            op2: Op2::Imm(imm), ..
+        }) => {
+            // Offset is relative to the next instruction
+            (addr + WORDSIZE).checked_add_signed(*imm as iarch as isize)
+        }
+        _ => None,
+    }
+}
+
+/// Formats an instruction as accepted by the assembler, naming PC-relative
+/// offsets using `offset`.
+fn render(instr: &Instruction, offset: impl Fn(uarch) -> String) -> String {
+    let op2 = |op2: &Op2| match *op2 {
+        Op2::Reg(reg) => lex::reg_name(reg, true),
+        Op2::Imm(imm) => offset(imm),
+    };
+    match instr {
+        Instruction::Bra(Bra {
+            op2: Op2::Imm(imm), ..
+        }) => {
+            let text = format!("{:#}", instr);
+            let (name, _) = text.split_once(' ').unwrap_or_default();
+            format!("{} {}", name, offset(*imm))
+        }
+        Instruction::Ldr(Ldr {
+            op1,
+            op2: operand,
+            mode: isa::inst::ldr::Mode::Ldr,
+        }) => format!("ldr {}, {}", lex::reg_name(*op1, true), op2(operand)),
+        Instruction::Str(Str {
+            op1,
+            op2: operand,
+            mode: isa::inst::str::Mode::Str,
+        }) => format!("str {}, {}", lex::reg_name(*op1, true), op2(operand)),
+        _ => format!("{:#}", instr),
+    }
+}
+
+#[derive(Debug)]
+pub enum DisError {
+    Truncated,
+}
+
+impl Display for DisError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Truncated => write!(f, "Image too short to hold the reset vector"),
+        }
+    }
+}
+
+impl Error for DisError {}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::link::Linker;
+    use crate::test_util::obj;
+
+    fn link(obj: Object) -> Vec<u8> {
+        let mut linker = Linker::new();
+        linker.add(obj);
+        linker.link().unwrap()
+    }
+
+    #[test]
+    fn roundtrip() {
+        let src = "
+            .entry
+            _main:
+                ldr a0, count
+                mov a1, 0x1
+            loop:
+                sub a0, 0x1
+                cmp a0, a1
+                ifgt
+                goto loop
+                str a0, count
+                push lr
+                pop lr
+                ldr a2, =0x4000
+                goto &lr
+            count:
+                .word 0xffff
+                .byte 0x7f
+        ";
+        for enc in [Encoding::Huffman, Encoding::Legacy] {
+            let obj = obj("main.s", src, enc);
+            let image = link(obj.clone());
+            // Targets are labelled, using symbols where known
+            let mut dis = Disassembler::new();
+            dis.encoding(enc);
+            let text = dis.dis(&image).unwrap();
+            let label = format!("L{:04x}", link::base(enc) + 0x4);
+            assert!(text.contains(&format!("{}:\n    sub a0, 0x0001", label)));
+            assert!(text.contains(&format!("goto {}", label)));
+            dis.add(obj);
+            let named = dis.dis(&image).unwrap();
+            assert!(named.contains(".entry\n_main:\n    ldr a0, count"));
+            assert!(named.contains("goto loop"));
+            assert!(named.contains(".word 0x4000"));
+            // Listings re-assemble to an identical image
+            for text in [text, named] {
+                assert_eq!(link(self::obj("dis.s", &text, enc)), image);
+            }
+        }
+        // Undecodable words are kept as data
+        let mut image = vec![0; link::VECTORS];
+        image[0] = 0x10;
+        image.extend([0x01, 0x0c]);
+        let text = Disassembler::new().dis(&image).unwrap();
+        assert!(text.contains(".entry\n    .word 0x0c01"));
+        assert!(Disassembler::new().dis(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn legacy() {
+        // Legacy ROMs have no vector table, and run from their first word
+        for rom in ["empty.rom", "full.rom"] {
+            let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../doc/roms")
+                .join(rom);
+            let image = std::fs::read(path).unwrap();
+            let mut dis = Disassembler::new();
+            dis.encoding(Encoding::Legacy);
+            let text = dis.dis(&image).unwrap();
+            assert!(text.contains("; Re-assemble using `--legacy`\n.entry\n"));
+            assert_eq!(link(obj(rom, &text, Encoding::Legacy)), image);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+use std::slice::Iter;
+
+use isa::lex;
+
+type Result<T> = std::result::Result<T, ExprError>;
+
+/// Evaluates a constant expression, resolving identifiers with `lookup`.
+///
+/// Operators follow C precedence, from loosest to tightest:
+///
+/// | Operators   | Operation                          |
+/// | ----------- | ---------------------------------- |
+/// | `\|`        | Bitwise OR                         |
+/// | `^`         | Bitwise XOR                        |
+/// | `&`         | Bitwise AND                        |
+/// | `<<` `>>`   | Shift left, arithmetic shift right |
+/// | `+` `-`     | Add, subtract                      |
+/// | `*` `/` `%` | Multiply, divide, remainder        |
+/// | `-` `~` `+` | Negate, complement (unary)         |
+pub fn eval<F>(tokens: &[String], lookup: F) -> Result<i32>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    let mut parser = Parser {
+        tokens: tokens.iter().peekable(),
+        lookup,
+    };
+    // Parse the whole expression
+    let value = parser.binary(0)?;
+    // Ensure all tokens were consumed
+    match parser.tokens.next() {
+        Some(token) => Err(ExprError::Unexpected(token.clone())),
+        None => Ok(value),
+    }
+}
+
+/// Formats an evaluated expression as an immediate token.
+pub fn imm(value: i32) -> Result<String> {
+    match value {
+        -0x8000..=0xffff => Ok(format!("{:#x}", value as u16)),
+        _ => Err(ExprError::Overflow),
+    }
+}
+
+struct Parser<'a, F> {
+    tokens: Peekable<Iter<'a, String>>,
+    lookup: F,
+}
+
+impl<F> Parser<'_, F>
+where
+    F: Fn(&str) -> Option<i32>,
+{
+    const LEVELS: [&'static [&'static str]; 6] = [
+        &["|"],
+        &["^"],
+        &["&"],
+        &["<<", ">>"],
+        &["+", "-"],
+        &["*", "/", "%"],
+    ];
+
+    fn binary(&mut self, level: usize) -> Result<i32> {
+        // Operands bind tighter than any binary operator
+        if level == Self::LEVELS.len() {
+            return self.unary();
+        }
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self
+            .tokens
+            .next_if(|token| Self::LEVELS[level].contains(&token.as_str()))
+        {
+            let rhs = self.binary(level + 1)?;
+            lhs = match op.as_str() {
+                "|" => Some(lhs | rhs),
+                "^" => Some(lhs ^ rhs),
+                "&" => Some(lhs & rhs),
+                "<<" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs)),
+                ">>" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs)),
+                "+" => lhs.checked_add(rhs),
+                "-" => lhs.checked_sub(rhs),
+                "*" => lhs.checked_mul(rhs),
+                "/" if rhs == 0 => return Err(ExprError::DivideByZero),
+                "/" => lhs.checked_div(rhs),
+                "%" if rhs == 0 => return Err(ExprError::DivideByZero),
+                _ => lhs.checked_rem(rhs),
+            }
+            .ok_or(ExprError::Overflow)?;
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<i32> {
+        let token = self.tokens.next().ok_or(ExprError::Empty)?;
+        match token.as_str() {
+            "-" => self.unary()?.checked_neg().ok_or(ExprError::Overflow),
+            "~" => Ok(!self.unary()?),
+            "+" => self.unary(),
+            "(" => {
+                let value = self.binary(0)?;
+                self.tokens
+                    .next_if(|token| *token == ")")
+                    .ok_or(ExprError::Unbalanced)?;
+                Ok(value)
+            }
+            token => self.atom(token),
+        }
+    }
+
+    fn atom(&self, token: &str) -> Result<i32> {
+        let first = token.chars().next().ok_or(ExprError::Empty)?;
+        match first {
+            // Numeric literals
+            '0'..='9' => lex::parse_imm(token)
+                .map(i32::from)
+                .map_err(|_| ExprError::Unexpected(token.to_string())),
+            // Character literals
+            '\'' => lex::parse_char(token)
+                .map(i32::from)
+                .map_err(|_| ExprError::Unexpected(token.to_string())),
+            // Symbols and constants
+            'A'..='Z' | 'a'..='z' | '_' => {
+                (self.lookup)(token).ok_or_else(|| ExprError::Undefined(token.to_string()))
+            }
+            _ => Err(ExprError::Unexpected(token.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExprError {
+    Empty,
+    Unexpected(String),
+    Unbalanced,
+    Undefined(String),
+    DivideByZero,
+    Overflow,
+    NotRelocatable,
+}
+
+impl Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Empty => "Expected expression".to_string(),
+                Self::Unexpected(token) => format!("Unexpected `{}` in expression", token),
+                Self::Unbalanced => "Unbalanced parentheses in expression".to_string(),
+                Self::Undefined(symbol) => format!("Undefined symbol `{}`", symbol),
+                Self::DivideByZero => "Division by zero in expression".to_string(),
+                Self::Overflow => "Expression overflows".to_string(),
+                Self::NotRelocatable => "Expression is not relocatable".to_string(),
+            }
+        )
+    }
+}
+
+impl Error for ExprError {}
+
+#[cfg(test)]
+mod tests {
+    use isa::lex;
+
+    use super::*;
+
+    fn calc(src: &str) -> Result<i32> {
+        let tokens = lex::tokenize(src).unwrap_or_default();
+        eval(&tokens, |symbol| match symbol {
+            "start" => Some(-6),
+            "end" => Some(4),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn precedence() {
+        // Precedence and associativity
+        assert_eq!(calc("0d1 + 0d2 * 0d3").unwrap(), 7);
+        assert_eq!(calc("(0d1 + 0d2) * 0d3").unwrap(), 9);
+        assert_eq!(calc("0d10 - 0d4 - 0d3").unwrap(), 3);
+        assert_eq!(calc("0b1 << 0x4 | 0x3 & ~0b10").unwrap(), 0x11);
+        assert_eq!(calc("-0d7 % 0d4 + 0o7").unwrap(), 4);
+        assert_eq!(calc("-0x100 >> 0x4").unwrap(), -0x10);
+        // Literals and symbols
+        assert_eq!(calc("'A' + 0x1").unwrap(), 0x42);
+        assert_eq!(calc("'\\n'").unwrap(), 0x0a);
+        assert_eq!(calc("end - start").unwrap(), 10);
+        // Malformed expressions
+        assert!(matches!(calc(""), Err(ExprError::Empty)));
+        assert!(matches!(calc("(0x1 + 0x2"), Err(ExprError::Unbalanced)));
+        assert!(matches!(calc("0x1 0x2"), Err(ExprError::Unexpected(_))));
+        assert!(matches!(calc("0d12"), Ok(12)));
+        assert!(matches!(calc("12"), Err(ExprError::Unexpected(_))));
+        assert!(matches!(calc("foo"), Err(ExprError::Undefined(_))));
+        assert!(matches!(calc("0x1 / 0x0"), Err(ExprError::DivideByZero)));
+        assert!(matches!(imm(0x10000), Err(ExprError::Overflow)));
+        assert_eq!(imm(-2).unwrap(), "0xfffe");
+    }
+}
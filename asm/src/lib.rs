@@ -5,34 +5,49 @@
 use std::error::Error;
 use std::fmt::{self, Display};
 use std::fs::File;
-use std::io::{self, BufRead, BufReader, Write};
-use std::mem;
+use std::io::{self, Write};
+use std::iter;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
-use colored::Colorize;
+use colored::{Color, Colorize};
+use isa::lex::{self, LexemeError};
+use isa::{uarch, WORDSIZE};
 use line::Line;
 
-mod inst;
-mod lex;
+mod data;
+mod dis;
+mod expr;
 mod line;
+mod link;
+mod obj;
+mod pool;
 mod prep;
 mod scope;
+#[cfg(test)]
+mod test_util;
 mod unit;
-mod util;
 
+use crate::expr::ExprError;
+use crate::prep::{Prep, PrepError};
+use crate::scope::ScopeError;
 use crate::unit::Unit;
 
-#[allow(non_camel_case_types)]
-type iarch = i16;
-#[allow(non_camel_case_types)]
-type uarch = u16;
+pub use isa::Encoding;
 
-const WORDSIZE: usize = mem::size_of::<uarch>();
+pub use crate::dis::{DisError, Disassembler};
+pub use crate::link::{LinkError, Linker};
+pub use crate::obj::{Bind, Kind, Object, ObjectError, Pool, Reloc, Section, Symbol, Target};
 
 #[derive(Debug, Default)]
 pub struct Assembler {
-    units: Vec<Unit>,
-    words: Vec<uarch>,
+    incs: Vec<PathBuf>,
+    units: Vec<(PathBuf, Unit)>,
+    objs: Vec<(PathBuf, Object)>,
+    bytes: Vec<u8>,
+    enc: Encoding,
+    strict: bool,
+    diags: Vec<VerboseError>,
 }
 
 impl Assembler {
@@ -40,53 +55,78 @@ impl Assembler {
         Default::default()
     }
 
-    pub fn src(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
-        // Open the input file
-        let f = File::open(&path)?;
+    pub fn encoding(&mut self, enc: Encoding) {
+        self.enc = enc;
+    }
+
+    /// Rejects immediates too wide to encode, rather than synthesizing them.
+    pub fn strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    pub fn include(&mut self, dir: &Path) {
+        self.incs.push(dir.to_path_buf());
+    }
+
+    /// Sources a file, recording any diagnostics.
+    pub fn src(&mut self, path: &Path) {
         // Read lines from file
-        let mut lines: Vec<Line> = BufReader::new(f)
-            .lines()
-            .collect::<Result<Vec<_>, _>>()?
-            .into_iter()
-            .enumerate()
-            .map(|(idx, line)| Line::new(idx, line))
-            .filter(|line| !line.tokens.is_empty())
-            .collect();
+        let lines = match line::read(path) {
+            Ok(lines) => lines,
+            Err(err) => return self.diags.push(VerboseError::file(err.into(), path)),
+        };
         // Perform preprocessing
-        prep::prep(&mut lines);
-        // Create a unit for this file
-        let unit = Unit::new(path.to_path_buf(), lines);
-        // Ensure no duplicate symbols
-        // TODO
-        // Create translation unit
-        self.units.push(unit);
-        Ok(())
+        let lines = match Prep::new(&self.incs).prep(path, lines) {
+            Ok(lines) => lines,
+            Err(errs) => return self.diags.extend(errs),
+        };
+        // Create a translation unit for this file
+        match Unit::new(lines) {
+            Ok(unit) => self.units.push((path.to_path_buf(), unit)),
+            Err(err) => self.diags.push(err),
+        }
+    }
+
+    /// Assembles each sourced file, recording any diagnostics.
+    pub fn asm(&mut self) {
+        // Assemble each translation unit into an object
+        for (path, unit) in self.units.drain(..) {
+            match unit.asm(self.enc, self.strict) {
+                Ok((obj, warnings)) => {
+                    self.diags.extend(warnings);
+                    self.objs.push((path, obj));
+                }
+                Err(diags) => self.diags.extend(diags),
+            }
+        }
+    }
+
+    /// Diagnostics recorded so far, in the order they were found.
+    pub fn diagnostics(&self) -> &[VerboseError] {
+        &self.diags
+    }
+
+    /// Assembled objects, along with the source they were assembled from.
+    pub fn objects(&self) -> impl Iterator<Item = (&Path, &Object)> {
+        self.objs.iter().map(|(path, obj)| (path.as_path(), obj))
     }
 
-    pub fn asm(&mut self) -> Result<(), Box<dyn Error>> {
-        // Concatenate translation units
-        // TODO: keep track of source file when concatenating
-        let unit = self.units.pop().unwrap_or_else(|| Unit::default());
-        let unit = self
-            .units
-            .clone()
-            .into_iter()
-            .try_fold(unit, Unit::concat)?;
-        // Assemble unit into binary
-        self.words = unit.asm()?;
+    pub fn link(&mut self) -> Result<(), Box<dyn Error>> {
+        // Link assembled objects into an image
+        let mut linker = Linker::new();
+        for (_, obj) in &self.objs {
+            linker.add(obj.clone());
+        }
+        self.bytes = linker
+            .link()
+            .map_err(|err| AsmError::from(Box::new(err) as Box<dyn Error>))?;
         Ok(())
     }
 
     pub fn out(&self, out: &Path) -> io::Result<()> {
         // Write to the output file
         let mut f = File::create(out)?;
-        f.write_all(
-            &self
-                .words
-                .iter()
-                .flat_map(|word| word.to_le_bytes())
-                .collect::<Vec<_>>(),
-        )
+        f.write_all(&self.bytes)
     }
 }
 
@@ -117,30 +157,247 @@ impl From<Box<dyn Error>> for AsmError {
 
 #[derive(Debug)]
 pub struct VerboseError {
+    level: Level,
     err: AsmError,
     loc: (PathBuf, usize),
     line: String,
+    span: Range<usize>,
+    notes: Vec<(String, (PathBuf, usize), String)>,
+}
+
+/// Severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warning,
 }
 
 impl Display for VerboseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let lineno = format!("{}", self.loc.1);
-        writeln!(f, "{}", self.err)?;
+        let (label, color) = match self.level {
+            Level::Error => ("error", Color::Red),
+            Level::Warning => ("warning", Color::Yellow),
+        };
+        let msg = format!("{}", self.err.err);
         writeln!(
             f,
-            "{}{} {}:{}",
-            " ".repeat(lineno.len()),
-            "-->".blue().bold(),
-            self.loc.0.display(),
-            self.loc.1,
+            "{}{} {}",
+            label.color(color).bold(),
+            ":".bold(),
+            msg.bold()
         )?;
-        writeln!(f, "{} {}", " ".repeat(lineno.len()), "|".blue().bold())?;
-        writeln!(f, "{} {}", format!("{} |", lineno).blue().bold(), self.line)?;
-        write!(f, "{} {}", " ".repeat(lineno.len()), "|".blue().bold())
+        snippet(f, &self.loc, &self.line, self.span.clone(), ('^', color))?;
+        // Point out any related lines
+        for (msg, loc, line) in &self.notes {
+            writeln!(f)?;
+            writeln!(f, "{}{} {}", "note".cyan().bold(), ":".bold(), msg.bold())?;
+            snippet(f, loc, line, statement(line), ('-', Color::Blue))?;
+        }
+        Ok(())
+    }
+}
+
+impl VerboseError {
+    fn new(err: Box<dyn Error>, line: &Line) -> Self {
+        // Underline the token at fault, or else the whole statement
+        let span = culprit(err.as_ref())
+            .and_then(|token| {
+                lex::spans(&line.text)
+                    .into_iter()
+                    .find(|span| line.text[span.clone()].eq_ignore_ascii_case(token))
+            })
+            .unwrap_or_else(|| statement(&line.text));
+        let mut this = Self {
+            level: Level::Error,
+            err: From::from(err),
+            loc: (line.path.clone(), line.number),
+            line: line.text.clone(),
+            span,
+            notes: Vec::new(),
+        };
+        this.trace(line);
+        this
+    }
+
+    /// Creates a diagnostic about a whole file.
+    fn file(err: Box<dyn Error>, path: &Path) -> Self {
+        Self {
+            level: Level::Error,
+            err: From::from(err),
+            loc: (path.to_path_buf(), 0),
+            line: String::new(),
+            span: 0..0,
+            notes: Vec::new(),
+        }
+    }
+
+    /// Demotes an error to a warning.
+    fn warning(mut self) -> Self {
+        self.level = Level::Warning;
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.level == Level::Error
+    }
+
+    /// Adds a note pointing out a related line.
+    fn note(mut self, msg: &str, line: &Line) -> Self {
+        self.notes.push((
+            msg.to_string(),
+            (line.path.clone(), line.number),
+            line.text.clone(),
+        ));
+        self.trace(line);
+        self
+    }
+
+    /// Traces back through any macro expansions of a line.
+    fn trace(&mut self, line: &Line) {
+        for site in iter::successors(line.site.as_deref(), |site| site.site.as_deref()) {
+            self.notes.push((
+                "in expansion of macro invoked here".to_string(),
+                (site.path.clone(), site.number),
+                site.text.clone(),
+            ));
+        }
+    }
+}
+
+/// Finds the token an error refers to, if any.
+fn culprit<'a>(err: &'a (dyn Error + 'static)) -> Option<&'a str> {
+    if let Some(err) = err.downcast_ref::<ExprError>() {
+        return match err {
+            ExprError::Unexpected(token) | ExprError::Undefined(token) => Some(token),
+            _ => None,
+        };
+    }
+    if let Some(err) = err.downcast_ref::<LexemeError>() {
+        return match err {
+            LexemeError::InvalidReg(token)
+            | LexemeError::InvalidImm(token)
+            | LexemeError::InvalidChar(token)
+            | LexemeError::InvalidStr(token) => Some(token),
+            LexemeError::EmptyToken => None,
+        };
+    }
+    if let Some(err) = err.downcast_ref::<ScopeError>() {
+        return match err {
+            ScopeError::Duplicate(symbol)
+            | ScopeError::Unused(symbol)
+            | ScopeError::UnusedExtern(symbol) => Some(symbol),
+            _ => None,
+        };
+    }
+    match err.downcast_ref::<PrepError>()? {
+        PrepError::Recursive(name) => Some(name),
+        _ => None,
     }
 }
 
+/// Spans the tokens of a line, excluding any comment.
+fn statement(text: &str) -> Range<usize> {
+    let spans = lex::spans(text);
+    match (spans.first(), spans.last()) {
+        (Some(first), Some(last)) => first.start..last.end,
+        _ => 0..0,
+    }
+}
+
+fn snippet(
+    f: &mut fmt::Formatter,
+    loc: &(PathBuf, usize),
+    line: &str,
+    span: Range<usize>,
+    marker: (char, Color),
+) -> fmt::Result {
+    // Whole files are referred to without a line
+    if line.is_empty() {
+        return write!(f, "{} {}", "-->".blue().bold(), loc.0.display());
+    }
+    // Lines and columns are counted from one
+    let lineno = format!("{}", loc.1 + 1);
+    let pad = " ".repeat(lineno.len());
+    writeln!(
+        f,
+        "{}{} {}:{}:{}",
+        pad,
+        "-->".blue().bold(),
+        loc.0.display(),
+        loc.1 + 1,
+        line[..span.start].chars().count() + 1,
+    )?;
+    writeln!(f, "{} {}", pad, "|".blue().bold())?;
+    writeln!(f, "{} {}", format!("{} |", lineno).blue().bold(), line)?;
+    // Underline the span, keeping any tabs for alignment
+    let indent: String = line[..span.start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let width = line[span].chars().count().max(1);
+    let underline = marker.0.to_string().repeat(width);
+    write!(
+        f,
+        "{} {} {}{}",
+        pad,
+        "|".blue().bold(),
+        indent,
+        underline.color(marker.1).bold(),
+    )
+}
+
 impl Error for VerboseError {}
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn diagnostics() {
+        let dir = env::temp_dir().join(format!("asm-diag-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.s");
+        fs::write(
+            &path,
+            ".entry\nstart:\n\tmov r0, missing\nunused:\n\tgoto start\n\tmov r1, 0b2\n",
+        )
+        .unwrap();
+        // All errors and warnings are reported in one run
+        let mut a = Assembler::new();
+        a.src(&path);
+        a.src(&dir.join("absent.s"));
+        a.asm();
+        let diags = a.diagnostics();
+        assert_eq!(diags.iter().filter(|diag| diag.is_error()).count(), 3);
+        assert_eq!(diags.iter().filter(|diag| !diag.is_error()).count(), 1);
+        // Spans underline the offending token
+        colored::control::set_override(false);
+        let undefined = diags
+            .iter()
+            .find(|diag| diag.loc.1 == 2)
+            .unwrap()
+            .to_string();
+        assert!(undefined.ends_with("3 | \tmov r0, missing\n  | \t        ^^^^^^^"));
+        let unused = diags.iter().find(|diag| !diag.is_error()).unwrap();
+        assert!(unused
+            .to_string()
+            .starts_with("warning: Unused label `unused`"));
+        assert!(unused.to_string().contains("main.s:4:1"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn programs() {
+        let prog = Path::new(env!("CARGO_MANIFEST_DIR")).join("../prog/src");
+        // The standard library and example programs assemble cleanly
+        for src in ["lib/std.s", "bin/fib.s", "bin/prime.s"] {
+            let mut a = Assembler::new();
+            a.src(&prog.join(src));
+            a.asm();
+            assert!(a.diagnostics().is_empty());
+            assert_eq!(a.objects().count(), 1);
+        }
+    }
+}
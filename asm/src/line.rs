@@ -1,4 +1,9 @@
-use crate::lex;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use isa::lex;
+
 use crate::scope::Scope;
 
 #[derive(Clone, Debug)]
@@ -9,18 +14,36 @@ pub enum Source {
 
 #[derive(Clone, Debug)]
 pub struct Line {
+    pub path: PathBuf,
     pub number: usize,
     pub text: String,
     pub tokens: Vec<String>,
+    pub site: Option<Box<Line>>,
 }
 
 impl Line {
-    pub fn new(number: usize, text: String) -> Self {
-        let tokens = lex::tokenize(&text).unwrap_or_else(Vec::new);
+    pub fn new(path: PathBuf, number: usize, text: String) -> Self {
+        let tokens = lex::tokenize(&text).unwrap_or_default();
         Self {
+            path,
             number,
             text,
             tokens,
+            site: None,
         }
     }
 }
+
+pub fn read(path: &Path) -> io::Result<Vec<Line>> {
+    // Open the input file
+    let f = File::open(path)?;
+    // Read non-empty lines from file
+    Ok(BufReader::new(f)
+        .lines()
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| Line::new(path.to_path_buf(), idx, line))
+        .filter(|line| !line.tokens.is_empty())
+        .collect())
+}
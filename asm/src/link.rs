@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use isa::{iarch, uarch, Encoding, Instruction, InstructionError, Op2, WORDSIZE};
+
+use crate::obj::{Bind, Kind, Object, Reloc, Symbol, Target};
+
+/// Size in bytes of the vector table, reserved at the start of the image.
+pub const VECTORS: usize = 0x10;
+
+/// Linker, combining relocatable objects into a memory image.
+///
+/// The image begins with the vector table, zeroed except for the reset vector,
+/// which holds the address of the single entry point declared by `.entry`.
+/// Sections follow, placed word aligned in the order their objects were added.
+/// Legacy images have no vector table, instead running from the start of
+/// memory, so their entry point must begin the image.
+#[derive(Debug, Default)]
+pub struct Linker {
+    objs: Vec<Object>,
+}
+
+impl Linker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, obj: Object) {
+        self.objs.push(obj);
+    }
+
+    pub fn link(&self) -> Result<Vec<u8>, LinkError> {
+        // Ensure all objects share an encoding
+        let enc = self.objs.first().map(|obj| obj.enc).unwrap_or_default();
+        if self.objs.iter().any(|obj| obj.enc != enc) {
+            return Err(LinkError::Encoding);
+        }
+        // Place each section following the vector table, if any
+        let mut image = vec![0; base(enc)];
+        let mut bases = Vec::with_capacity(self.objs.len());
+        for obj in &self.objs {
+            let mut base = Vec::with_capacity(obj.sections.len());
+            for section in &obj.sections {
+                image.resize(image.len().next_multiple_of(WORDSIZE), 0);
+                base.push(image.len());
+                image.extend(&section.data);
+            }
+            bases.push(base);
+        }
+        // Build the global symbol table
+        let mut globals = HashMap::new();
+        for (obj, base) in self.objs.iter().zip(&bases) {
+            let defined = obj
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.bind == Bind::Global);
+            for symbol in defined {
+                let Some(section) = symbol.section else {
+                    continue;
+                };
+                let addr = base.get(section).ok_or(LinkError::Malformed)? + symbol.value;
+                if let Some((_, prev)) = globals.insert(symbol.name.as_str(), (addr, symbol)) {
+                    return Err(LinkError::Duplicate(
+                        symbol.name.clone(),
+                        site(prev),
+                        site(symbol),
+                    ));
+                }
+            }
+        }
+        // Apply each relocation
+        for (obj, base) in self.objs.iter().zip(&bases) {
+            for reloc in &obj.relocs {
+                // Find the referenced address
+                let target = match &reloc.target {
+                    Target::Section(idx) => base.get(*idx).copied().ok_or(LinkError::Malformed)?,
+                    Target::Symbol(name) => match globals.get(name.as_str()) {
+                        Some(&(addr, _)) => addr,
+                        None => {
+                            // Point to where the symbol was declared external
+                            let decl = obj
+                                .symbols
+                                .iter()
+                                .find(|symbol| symbol.name == *name && symbol.section.is_none());
+                            return Err(LinkError::Undefined(name.clone(), decl.map(site)));
+                        }
+                    },
+                };
+                let place = base.get(reloc.section).ok_or(LinkError::Malformed)? + reloc.offset;
+                patch(&mut image, place, target, reloc, enc).map_err(|err| {
+                    err.unwrap_or_else(|| LinkError::OutOfRange(name(obj, reloc)))
+                })?;
+            }
+        }
+        // Ensure exactly one entry point is declared
+        let mut entries = self
+            .objs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, obj)| Some((idx, obj.entry.as_ref()?)));
+        let (owner, entry) = entries.next().ok_or(LinkError::NoEntry)?;
+        if let Some((_, next)) = entries.next() {
+            return Err(LinkError::MultipleEntries(site(entry), site(next)));
+        }
+        let addr = bases[owner]
+            .get(entry.section.ok_or(LinkError::Malformed)?)
+            .ok_or(LinkError::Malformed)?
+            + entry.value;
+        match enc {
+            // Point the reset vector to the entry
+            Encoding::Huffman => image[..WORDSIZE].copy_from_slice(&(addr as uarch).to_le_bytes()),
+            // Execution begins at the start of the image
+            Encoding::Legacy if addr != 0 => return Err(LinkError::LegacyEntry(site(entry))),
+            Encoding::Legacy => (),
+        }
+        Ok(image)
+    }
+}
+
+/// Finds the address at which sections are placed for an encoding.
+pub fn base(enc: Encoding) -> usize {
+    match enc {
+        Encoding::Huffman => VECTORS,
+        Encoding::Legacy => 0,
+    }
+}
+
+/// Patches the relocated field at `place` to refer to `target`, yielding
+/// `None` for values out of range.
+fn patch(
+    image: &mut [u8],
+    place: usize,
+    target: usize,
+    reloc: &Reloc,
+    enc: Encoding,
+) -> Result<(), Option<LinkError>> {
+    let field = image
+        .get_mut(place..place + WORDSIZE)
+        .ok_or(Some(LinkError::Malformed))?;
+    let value = target as i32 + reloc.addend;
+    let word = match reloc.kind {
+        Kind::Abs => uarch::try_from(value).map_err(|_| None)?,
+        Kind::Rel => {
+            // Offset is relative to the next instruction
+            let offset = value - (place + WORDSIZE) as i32;
+            if !(-0x80..0x80).contains(&offset) || offset % WORDSIZE as i32 != 0 {
+                return Err(None);
+            }
+            let word = uarch::from_le_bytes([field[0], field[1]]);
+            let mut instr = Instruction::decode(word, enc).map_err(|err| Some(err.into()))?;
+            match &mut instr {
+                Instruction::Bra(isa::inst::bra::Bra { op2, .. })
+                | Instruction::Ldr(isa::inst::ldr::Ldr { op2, .. })
+                | Instruction::Str(isa::inst::str::Str { op2, .. }) => {
+                    *op2 = Op2::Imm(offset as iarch as uarch)
+                }
+                _ => return Err(Some(LinkError::Unsupported(format!("{}", instr)))),
+            }
+            instr.encode(enc).map_err(|err| Some(err.into()))?
+        }
+    };
+    field.copy_from_slice(&word.to_le_bytes());
+    Ok(())
+}
+
+fn site(symbol: &Symbol) -> String {
+    format!("{}:{}", symbol.path, symbol.line + 1)
+}
+
+fn name(obj: &Object, reloc: &Reloc) -> String {
+    match &reloc.target {
+        Target::Section(idx) => obj.sections.get(*idx).map_or_else(
+            || format!("section {}", idx),
+            |section| section.name.clone(),
+        ),
+        Target::Symbol(name) => name.clone(),
+    }
+}
+
+#[derive(Debug)]
+pub enum LinkError {
+    Encoding,
+    Malformed,
+    NoEntry,
+    MultipleEntries(String, String),
+    LegacyEntry(String),
+    Undefined(String, Option<String>),
+    Duplicate(String, String, String),
+    OutOfRange(String),
+    Unsupported(String),
+    Instruction(InstructionError),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Encoding => write!(f, "Objects use differing instruction encodings"),
+            Self::Malformed => write!(f, "Malformed object file"),
+            Self::NoEntry => write!(f, "No entry point declared; use `.entry`"),
+            Self::MultipleEntries(prev, next) => write!(
+                f,
+                "Multiple entry points declared, at {} and {}",
+                prev, next
+            ),
+            Self::LegacyEntry(entry) => write!(
+                f,
+                "Entry point at {} must begin a legacy image, which has no reset vector",
+                entry
+            ),
+            Self::Undefined(symbol, None) => write!(f, "Undefined symbol `{}`", symbol),
+            Self::Undefined(symbol, Some(decl)) => write!(
+                f,
+                "Undefined symbol `{}`, declared external at {}",
+                symbol, decl
+            ),
+            Self::Duplicate(symbol, prev, next) => write!(
+                f,
+                "Multiply defined symbol `{}`, defined at {} and {}",
+                symbol, prev, next
+            ),
+            Self::OutOfRange(symbol) => write!(f, "Relocation against `{}` out of range", symbol),
+            Self::Unsupported(instr) => write!(f, "Cannot relocate `{}`", instr),
+            Self::Instruction(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for LinkError {}
+
+impl From<InstructionError> for LinkError {
+    fn from(err: InstructionError) -> Self {
+        Self::Instruction(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn obj(path: &str, src: &str) -> Object {
+        test_util::obj(path, src, Encoding::Huffman)
+    }
+
+    #[test]
+    fn resolve() {
+        let main = obj(
+            "main.s",
+            "
+            .global _main
+            .extern msg, count
+            _main:
+                ldr r0, =msg
+                ldr r1, count
+                hlt
+            ptr:
+                .word ptr
+        ",
+        );
+        let lib = obj(
+            "lib.s",
+            "
+            .global count, msg
+            .extern _main
+            count:
+                .word _main
+            .entry
+            loop:
+                hlt
+            msg:
+                .word msg + 0x2, loop
+        ",
+        );
+        // Unresolved fields are recorded as relocations
+        assert_eq!(main.relocs.len(), 3);
+        assert!(main.relocs.iter().any(|reloc| reloc.kind == Kind::Rel));
+        assert!(lib
+            .symbols
+            .iter()
+            .any(|symbol| symbol.name == "loop" && symbol.bind == Bind::Local));
+        assert_eq!(lib.entry.as_ref().unwrap().name, "loop");
+        // Linking places objects in order, after the vector table
+        let mut linker = Linker::new();
+        linker.add(main.clone());
+        linker.add(lib.clone());
+        let image = linker.link().unwrap();
+        assert_eq!(
+            image[..VECTORS],
+            [0x1c, 0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(
+            image[VECTORS..],
+            [
+                0x83, 0x30, 0x83, 0x31, 0x00, 0x0c, 0x16, 0x00, 0x1e, 0x00, 0x10, 0x00, 0x00, 0x0c,
+                0x20, 0x00, 0x1c, 0x00,
+            ]
+        );
+        // Symbols must be defined exactly once
+        let mut linker = Linker::new();
+        linker.add(main.clone());
+        assert_eq!(
+            linker.link().unwrap_err().to_string(),
+            "Undefined symbol `msg`, declared external at main.s:3"
+        );
+        linker.add(lib);
+        linker.add(obj("dup.s", "\n.global count\ncount:"));
+        assert_eq!(
+            linker.link().unwrap_err().to_string(),
+            "Multiply defined symbol `count`, defined at lib.s:4 and dup.s:3"
+        );
+        // Exactly one entry point must be declared
+        let mut linker = Linker::new();
+        linker.add(obj("none.s", "hlt"));
+        assert!(matches!(linker.link(), Err(LinkError::NoEntry)));
+        linker.add(obj("one.s", ".entry\nhlt"));
+        linker.add(obj("two.s", "hlt\n.entry\nhlt"));
+        assert_eq!(
+            linker.link().unwrap_err().to_string(),
+            "Multiple entry points declared, at one.s:1 and two.s:2"
+        );
+    }
+
+    #[test]
+    fn legacy() {
+        let obj = |path, src| test_util::obj(path, src, Encoding::Legacy);
+        // Legacy images have no vector table, beginning at the entry point
+        let mut linker = Linker::new();
+        linker.add(obj("main.s", ".entry\nloop:\ngoto loop"));
+        linker.add(obj("data.s", ".word 0x1234"));
+        let image = linker.link().unwrap();
+        let goto =
+            Instruction::decode(uarch::from_le_bytes([image[0], image[1]]), Encoding::Legacy);
+        assert_eq!(goto.unwrap().to_string(), "goto 0xfffe");
+        assert_eq!(image[2..], [0x34, 0x12]);
+        // The entry point cannot be placed elsewhere
+        let mut linker = Linker::new();
+        linker.add(obj("data.s", ".word 0x1234"));
+        linker.add(obj("main.s", "hlt\n.entry\nhlt"));
+        assert_eq!(
+            linker.link().unwrap_err().to_string(),
+            "Entry point at main.s:2 must begin a legacy image, which has no reset vector"
+        );
+    }
+}
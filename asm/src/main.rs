@@ -1,8 +1,10 @@
+use std::fs::File;
 use std::path::PathBuf;
 use std::process;
 
-use asm::Assembler;
+use asm::{Assembler, Encoding};
 use clap::{Parser, ValueHint};
+use colored::Colorize;
 use env_logger as logger;
 use log::error;
 
@@ -19,21 +21,75 @@ fn main() {
 
     // Instantiate an assembler
     let mut a = Assembler::new();
+    // Select the instruction encoding
+    if args.legacy {
+        a.encoding(Encoding::Legacy);
+    }
+    // Reject wide immediates if requested
+    a.strict(args.strict);
+    // Add each include path
+    for dir in &args.include {
+        a.include(dir);
+    }
     // Source each input file
     for file in &args.srcs {
-        a.src(file).unwrap_or_else(|err| {
-            eprintln!("{}: `{}`", err, file.display());
-            process::exit(1);
-        });
+        a.src(file);
     }
     // Produce an assembled output
-    a.asm().unwrap_or_else(|err| {
+    a.asm();
+    // Report all diagnostics, aborting on errors
+    let diags = a.diagnostics();
+    for diag in diags {
+        eprintln!("{}\n", diag);
+    }
+    let errors = diags.iter().filter(|diag| diag.is_error()).count();
+    let warnings = diags.len() - errors;
+    let emitted = match warnings {
+        0 => None,
+        1 => Some("1 warning emitted".to_string()),
+        n => Some(format!("{} warnings emitted", n)),
+    };
+    if errors > 0 {
+        let aborting = match errors {
+            1 => "aborting due to previous error".to_string(),
+            n => format!("aborting due to {} previous errors", n),
+        };
+        match emitted {
+            Some(emitted) => eprintln!("{}: {}; {}", "error".red().bold(), aborting, emitted),
+            None => eprintln!("{}: {}", "error".red().bold(), aborting),
+        }
+        process::exit(1);
+    } else if let Some(emitted) = emitted {
+        eprintln!("{}: {}\n", "warning".yellow().bold(), emitted);
+    }
+    // Write object files without linking
+    if args.compile {
+        if args.out.is_some() && args.srcs.len() > 1 {
+            error!("cannot specify `--out` with `-c` and multiple sources");
+            process::exit(1);
+        }
+        for (src, obj) in a.objects() {
+            let out = args.out.clone().unwrap_or_else(|| {
+                PathBuf::from(src.file_name().unwrap_or_default()).with_extension("o")
+            });
+            File::create(&out)
+                .and_then(|mut f| obj.write(&mut f))
+                .unwrap_or_else(|err| {
+                    error!("{}: `{}`", err, out.display());
+                    process::exit(1);
+                });
+        }
+        return;
+    }
+    // Link objects into an executable
+    a.link().unwrap_or_else(|err| {
         eprintln!("{}", err);
         process::exit(1);
     });
     // Write output file
-    a.out(&args.out).unwrap_or_else(|err| {
-        error!("{}: `{}`", err, &args.out.display());
+    let out = args.out.unwrap_or_else(|| PathBuf::from("a.out"));
+    a.out(&out).unwrap_or_else(|err| {
+        error!("{}: `{}`", err, out.display());
         process::exit(1);
     });
 }
@@ -48,12 +104,29 @@ struct Args {
     #[clap(value_hint = ValueHint::FilePath)]
     srcs: Vec<PathBuf>,
 
-    /// Output binary file
+    /// Output file [default: a.out, or <src>.o with -c]
     #[clap(short, long)]
     #[clap(parse(from_os_str))]
-    #[clap(default_value = "a.out")]
     #[clap(value_hint = ValueHint::FilePath)]
-    out: PathBuf,
+    out: Option<PathBuf>,
+
+    /// Assemble into relocatable objects, without linking
+    #[clap(short = 'c')]
+    compile: bool,
+
+    /// Search directory for included files
+    #[clap(short = 'I', long)]
+    #[clap(parse(from_os_str))]
+    #[clap(value_hint = ValueHint::DirPath)]
+    include: Vec<PathBuf>,
+
+    /// Encode using the legacy fixed-width opcodes
+    #[clap(long)]
+    legacy: bool,
+
+    /// Reject immediates too wide to encode, rather than synthesizing them
+    #[clap(long)]
+    strict: bool,
 
     /// Use verbose output (-v, -vv, -vvv, etc.)
     #[clap(short, long)]
@@ -0,0 +1,378 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+
+use isa::Encoding;
+
+/// Leading bytes identifying an object file.
+const MAGIC: &[u8; 4] = b"KAPo";
+
+/// Relocatable object, as produced by assembling a single translation unit.
+///
+/// Objects are serialized as little-endian fields in the following order,
+/// with strings prefixed by their 16-bit length:
+///
+/// | Field      | Contents                                                   |
+/// | ---------- | ---------------------------------------------------------- |
+/// | Header     | `KAPo` magic, encoding (`u8`)                              |
+/// | Sections   | Count (`u16`), then each name and data (`u16` length)      |
+/// | Symbols    | Count (`u16`), then each name, binding, section, value,    |
+/// |            | source path and line (`u32`)                               |
+/// | Relocs     | Count (`u16`), then each section, offset, kind, target and |
+/// |            | addend (`i16`)                                             |
+/// | Pools      | Count (`u16`), then each section, offset and size          |
+/// | Entry      | Presence (`u8`), then a symbol                             |
+///
+/// An undefined symbol, declared by `.extern`, has a section index of
+/// `0xffff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Object {
+    pub enc: Encoding,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocs: Vec<Reloc>,
+    pub pools: Vec<Pool>,
+    pub entry: Option<Symbol>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub bind: Bind,
+    pub section: Option<usize>,
+    pub value: usize,
+    pub path: String,
+    pub line: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bind {
+    Local,
+    Global,
+}
+
+/// Location to be patched with the address of a symbol once it is known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reloc {
+    pub section: usize,
+    pub offset: usize,
+    pub kind: Kind,
+    pub target: Target,
+    pub addend: i32,
+}
+
+/// Literal pool placed within a section, holding data amongst the code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pool {
+    pub section: usize,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Word holding `S + A`
+    Abs,
+    /// Instruction immediate holding `S + A - P`, where `P` is the address of
+    /// the next instruction
+    Rel,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Start of a section within this object
+    Section(usize),
+    /// Symbol by name, possibly defined in another object
+    Symbol(String),
+}
+
+impl Object {
+    pub fn read(r: &mut impl Read) -> Result<Self, ObjectError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        (&magic == MAGIC)
+            .then_some(())
+            .ok_or(ObjectError::BadMagic)?;
+        let enc = match read_u8(r)? {
+            0 => Encoding::Huffman,
+            1 => Encoding::Legacy,
+            _ => return Err(ObjectError::Malformed),
+        };
+        let sections = (0..read_u16(r)?)
+            .map(|_| {
+                let name = read_str(r)?;
+                let mut data = vec![0; read_u16(r)?.into()];
+                r.read_exact(&mut data)?;
+                Ok(Section { name, data })
+            })
+            .collect::<Result<_, ObjectError>>()?;
+        let symbols = (0..read_u16(r)?)
+            .map(|_| read_symbol(r))
+            .collect::<Result<_, ObjectError>>()?;
+        let relocs = (0..read_u16(r)?)
+            .map(|_| {
+                Ok(Reloc {
+                    section: read_u16(r)?.into(),
+                    offset: read_u16(r)?.into(),
+                    kind: match read_u8(r)? {
+                        0 => Kind::Abs,
+                        1 => Kind::Rel,
+                        _ => return Err(ObjectError::Malformed),
+                    },
+                    target: match read_u8(r)? {
+                        0 => Target::Section(read_u16(r)?.into()),
+                        1 => Target::Symbol(read_str(r)?),
+                        _ => return Err(ObjectError::Malformed),
+                    },
+                    addend: read_u16(r)? as i16 as i32,
+                })
+            })
+            .collect::<Result<_, ObjectError>>()?;
+        let pools = (0..read_u16(r)?)
+            .map(|_| {
+                Ok(Pool {
+                    section: read_u16(r)?.into(),
+                    offset: read_u16(r)?.into(),
+                    size: read_u16(r)?.into(),
+                })
+            })
+            .collect::<Result<_, ObjectError>>()?;
+        let entry = match read_u8(r)? {
+            0 => None,
+            1 => Some(read_symbol(r)?),
+            _ => return Err(ObjectError::Malformed),
+        };
+        Ok(Self {
+            enc,
+            sections,
+            symbols,
+            relocs,
+            pools,
+            entry,
+        })
+    }
+
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&[match self.enc {
+            Encoding::Huffman => 0,
+            Encoding::Legacy => 1,
+        }])?;
+        write_u16(w, self.sections.len())?;
+        for section in &self.sections {
+            write_str(w, &section.name)?;
+            write_u16(w, section.data.len())?;
+            w.write_all(&section.data)?;
+        }
+        write_u16(w, self.symbols.len())?;
+        for symbol in &self.symbols {
+            write_symbol(w, symbol)?;
+        }
+        write_u16(w, self.relocs.len())?;
+        for reloc in &self.relocs {
+            write_u16(w, reloc.section)?;
+            write_u16(w, reloc.offset)?;
+            w.write_all(&[reloc.kind as u8])?;
+            match &reloc.target {
+                Target::Section(idx) => {
+                    w.write_all(&[0])?;
+                    write_u16(w, *idx)?;
+                }
+                Target::Symbol(name) => {
+                    w.write_all(&[1])?;
+                    write_str(w, name)?;
+                }
+            }
+            write_u16(w, reloc.addend as u16 as usize)?;
+        }
+        write_u16(w, self.pools.len())?;
+        for pool in &self.pools {
+            write_u16(w, pool.section)?;
+            write_u16(w, pool.offset)?;
+            write_u16(w, pool.size)?;
+        }
+        match &self.entry {
+            Some(entry) => {
+                w.write_all(&[1])?;
+                write_symbol(w, entry)?;
+            }
+            None => w.write_all(&[0])?,
+        }
+        Ok(())
+    }
+}
+
+fn read_symbol(r: &mut impl Read) -> Result<Symbol, ObjectError> {
+    Ok(Symbol {
+        name: read_str(r)?,
+        bind: match read_u8(r)? {
+            0 => Bind::Local,
+            1 => Bind::Global,
+            _ => return Err(ObjectError::Malformed),
+        },
+        section: match read_u16(r)? {
+            0xffff => None,
+            idx => Some(idx.into()),
+        },
+        value: read_u16(r)?.into(),
+        path: read_str(r)?,
+        line: read_u32(r)? as usize,
+    })
+}
+
+fn write_symbol(w: &mut impl Write, symbol: &Symbol) -> io::Result<()> {
+    write_str(w, &symbol.name)?;
+    w.write_all(&[symbol.bind as u8])?;
+    write_u16(w, symbol.section.unwrap_or(0xffff))?;
+    write_u16(w, symbol.value)?;
+    write_str(w, &symbol.path)?;
+    write_u32(w, symbol.line)
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16(r: &mut impl Read) -> io::Result<u16> {
+    let mut buf = [0; 2];
+    r.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_str(r: &mut impl Read) -> Result<String, ObjectError> {
+    let mut buf = vec![0; read_u16(r)?.into()];
+    r.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|_| ObjectError::Malformed)
+}
+
+fn write_u16(w: &mut impl Write, value: usize) -> io::Result<()> {
+    let value = u16::try_from(value).map_err(|_| io::ErrorKind::InvalidInput)?;
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_u32(w: &mut impl Write, value: usize) -> io::Result<()> {
+    let value = u32::try_from(value).map_err(|_| io::ErrorKind::InvalidInput)?;
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_u16(w, s.len())?;
+    w.write_all(s.as_bytes())
+}
+
+#[derive(Debug)]
+pub enum ObjectError {
+    BadMagic,
+    Malformed,
+    Io(io::Error),
+}
+
+impl Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "Not an object file"),
+            Self::Malformed => write!(f, "Malformed object file"),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ObjectError {}
+
+impl From<io::Error> for ObjectError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => Self::Malformed,
+            _ => Self::Io(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let obj = Object {
+            enc: Encoding::Legacy,
+            sections: vec![Section {
+                name: "text".to_string(),
+                data: vec![0x00, 0x0c, 0x00, 0x00],
+            }],
+            symbols: vec![
+                Symbol {
+                    name: "_main".to_string(),
+                    bind: Bind::Global,
+                    section: Some(0),
+                    value: 0x0000,
+                    path: "main.s".to_string(),
+                    line: 3,
+                },
+                Symbol {
+                    name: "putc".to_string(),
+                    bind: Bind::Global,
+                    section: None,
+                    value: 0x0000,
+                    path: "main.s".to_string(),
+                    line: 1,
+                },
+            ],
+            relocs: vec![
+                Reloc {
+                    section: 0,
+                    offset: 0x0002,
+                    kind: Kind::Abs,
+                    target: Target::Symbol("putc".to_string()),
+                    addend: -0x2,
+                },
+                Reloc {
+                    section: 0,
+                    offset: 0x0000,
+                    kind: Kind::Rel,
+                    target: Target::Section(0),
+                    addend: 0x4,
+                },
+            ],
+            pools: vec![Pool {
+                section: 0,
+                offset: 0x0002,
+                size: 0x0002,
+            }],
+            entry: Some(Symbol {
+                name: "_main".to_string(),
+                bind: Bind::Local,
+                section: Some(0),
+                value: 0x0000,
+                path: "main.s".to_string(),
+                line: 2,
+            }),
+        };
+        let mut buf = Vec::new();
+        obj.write(&mut buf).unwrap();
+        assert_eq!(Object::read(&mut &buf[..]).unwrap(), obj);
+        // Truncated and foreign files are rejected
+        assert!(matches!(
+            Object::read(&mut &buf[..buf.len() - 1]),
+            Err(ObjectError::Malformed)
+        ));
+        assert!(matches!(
+            Object::read(&mut &b"\x7fELF"[..]),
+            Err(ObjectError::BadMagic)
+        ));
+    }
+}
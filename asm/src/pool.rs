@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use isa::inst::bra::Bra;
+use isa::Cond;
+use isa::{lex, Encoding, Instruction, Op2};
+
+use crate::expr;
+use crate::line::Line;
+use crate::{uarch, WORDSIZE};
+
+/// Furthest forward offset reachable by a PC-relative load.
+const RANGE: usize = 0x7e;
+
+/// Literal pool, holding the constants of `ldr rX, =EXPR` loads.
+///
+/// Literals are placed in the next `.pool` (or `.ltorg`) directive, or at the
+/// end of the unit. Should a literal fall out of range of its load, a pool is
+/// placed early along with a branch over it.
+#[derive(Debug, Default)]
+pub struct Pool {
+    pending: Vec<(usize, Line)>,
+    entries: Vec<usize>,
+    next: usize,
+    values: VecDeque<String>,
+}
+
+impl Pool {
+    /// Records a literal load placed at `addr`.
+    pub fn push(&mut self, addr: usize, line: &Line) {
+        self.pending.push((addr, line.clone()));
+    }
+
+    /// Checks if pending literals remain in range of a pool placed at `addr`.
+    pub fn reaches(&self, addr: usize) -> bool {
+        self.pending.first().is_none_or(|(load, _)| {
+            let entry = addr.next_multiple_of(WORDSIZE) + WORDSIZE;
+            entry - (load + WORDSIZE) <= RANGE
+        })
+    }
+
+    /// Places all pending literals into a pool at `line`, optionally preceded
+    /// by a branch over it.
+    pub fn flush(&mut self, line: &Line, skip: bool) -> Line {
+        let count = self.pending.drain(..).count();
+        Line {
+            tokens: [".", "pool", &imm(count), ",", &imm(skip as usize)]
+                .map(String::from)
+                .to_vec(),
+            ..line.clone()
+        }
+    }
+
+    /// Places any remaining literals into a pool at the end of the unit.
+    pub fn finish(&mut self) -> Option<Line> {
+        let line = self.pending.last()?.1.clone();
+        Some(self.flush(&line, false))
+    }
+
+    /// Records the address of each entry in a placed pool at `addr`.
+    pub fn update(&mut self, tokens: &[String], addr: usize) {
+        if let Some(entries) = entries(tokens, addr) {
+            self.entries.extend(entries.step_by(WORDSIZE));
+        }
+    }
+
+    /// Resolves a literal load at `addr` to its entry, returning the entry's
+    /// address and offset from the next instruction.
+    ///
+    /// The entry is taken even if the value is missing or too wide, keeping
+    /// later loads aligned with their entries.
+    pub fn load(
+        &mut self,
+        value: Option<i32>,
+        addr: usize,
+    ) -> Result<(usize, String), Box<dyn Error>> {
+        let entry = self.entries[self.next];
+        self.next += 1;
+        let value = value.map_or_else(|| Ok(imm(0)), expr::imm);
+        self.values
+            .push_back(value.as_ref().map_or_else(|_| imm(0), String::clone));
+        value?;
+        Ok((entry, imm(entry - (addr + WORDSIZE))))
+    }
+
+    /// Fills a placed pool with the values of its literals.
+    pub fn fill(&mut self, tokens: &mut Vec<String>) {
+        if let Some((count, _)) = header(tokens) {
+            for value in self.values.drain(..count) {
+                tokens.push(",".to_string());
+                tokens.push(value);
+            }
+        }
+    }
+}
+
+/// Checks if an instruction loads a literal.
+pub fn literal(tokens: &[String]) -> bool {
+    matches!(tokens, [ldr, _, sep, eq, ..] if ldr.eq_ignore_ascii_case("ldr") && sep == "," && eq == "=")
+}
+
+/// Checks if a line is a pool placed early, rather than by the source.
+pub fn early(tokens: &[String]) -> bool {
+    matches!(header(tokens), Some((_, skip)) if skip != 0)
+}
+
+/// Byte range of the entries of a placed pool at `addr`.
+pub fn entries(tokens: &[String], addr: usize) -> Option<Range<usize>> {
+    let (count, skip) = header(tokens)?;
+    let base = addr.next_multiple_of(WORDSIZE) + skip * WORDSIZE;
+    Some(base..base + count * WORDSIZE)
+}
+
+/// Size in bytes of a placed pool at `addr`.
+pub fn size(tokens: &[String], addr: usize) -> usize {
+    match header(tokens) {
+        Some((0, _)) | None => 0,
+        Some((count, skip)) => addr.next_multiple_of(WORDSIZE) - addr + (skip + count) * WORDSIZE,
+    }
+}
+
+/// Emits the bytes of a filled pool placed at `addr`.
+pub fn emit(tokens: &[String], addr: usize, enc: Encoding) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (count, skip) = header(tokens).ok_or(PoolError::Malformed)?;
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    // Align the pool to a word
+    let mut bytes = vec![0; addr.next_multiple_of(WORDSIZE) - addr];
+    // Branch over the pool if placed inline
+    if skip != 0 {
+        let offset = count * WORDSIZE;
+        (offset <= RANGE)
+            .then_some(())
+            .ok_or(PoolError::OutOfRange)?;
+        let instr = Instruction::Bra(Bra {
+            op2: Op2::Imm(offset as uarch),
+            link: false,
+            cond: Cond::Al,
+        });
+        bytes.extend(instr.encode(enc)?.to_le_bytes());
+    }
+    // Emit each literal
+    for value in tokens[6..].iter().step_by(2) {
+        bytes.extend(lex::parse_imm(value)?.to_le_bytes());
+    }
+    Ok(bytes)
+}
+
+fn header(tokens: &[String]) -> Option<(usize, usize)> {
+    match tokens {
+        [dot, pool, count, _, skip, ..] if dot == "." && pool == "pool" => Some((
+            lex::parse_imm(count).ok()?.into(),
+            lex::parse_imm(skip).ok()?.into(),
+        )),
+        _ => None,
+    }
+}
+
+fn imm(value: usize) -> String {
+    format!("{:#x}", value)
+}
+
+#[derive(Debug)]
+pub enum PoolError {
+    Malformed,
+    OutOfRange,
+}
+
+impl Display for PoolError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Malformed => "Malformed literal pool",
+                Self::OutOfRange => "Literal pool too large to branch over",
+            }
+        )
+    }
+}
+
+impl Error for PoolError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::lines;
+    use crate::unit::Unit;
+
+    fn unit(src: &str) -> Unit {
+        Unit::new(lines("test.s", src)).unwrap()
+    }
+
+    fn asm(src: &str) -> Vec<u8> {
+        let (obj, _) = unit(src).asm(Encoding::Huffman, false).unwrap();
+        obj.sections[0].data.clone()
+    }
+
+    #[test]
+    fn literals() {
+        // Explicit pools take pending literals
+        let src = "
+            ldr sp, =0x4000
+            ldr r0, =data + 0x1
+            hlt
+            .ltorg
+            data:
+            ldr r1, =-0x1
+        ";
+        assert_eq!(
+            asm(src),
+            [0x82, 0x3d, 0x82, 0x30, 0x00, 0x0c, 0x00, 0x40, 0x0b, 0x00, 0x80, 0x31, 0xff, 0xff]
+        );
+        // Out of range literals are placed early, branching over the pool
+        let src = "
+            ldr r0, =0x1234
+            .repeat 0d63
+            mov r0, r0
+            hlt
+        ";
+        let bytes = asm(src);
+        assert_eq!(bytes.len(), 0x86);
+        assert_eq!(bytes[..2], [0xbf, 0x30]);
+        assert_eq!(bytes[0x7e..0x82], [0x81, 0x00, 0x34, 0x12]);
+        assert_eq!(bytes[0x82..], [0x00, 0x70, 0x00, 0x0c]);
+        // Nor between an `if` and the line it predicates
+        let src = "
+            ldr r0, =0x1234
+            .repeat 0d61
+            mov r0, r0
+            ifne
+            mov r1, r2
+            hlt
+        ";
+        let bytes = asm(src);
+        assert_eq!(bytes.len(), 0x86);
+        assert_eq!(bytes[0x7c..0x80], [0x81, 0x00, 0x34, 0x12]);
+        assert_eq!(bytes[0x80..0x82], [0x03, 0x0e]);
+    }
+
+    #[test]
+    fn invalid() {
+        // Bad literals are reported, without upsetting later loads
+        for literal in ["=missing", "=", "=0x1 +", "=0x10000"] {
+            let src = format!("ldr r0, {}\nldr r1, =0x1234", literal);
+            let errs = unit(&src).asm(Encoding::Huffman, false).unwrap_err();
+            assert_eq!(errs.len(), 1);
+            assert_eq!(errs[0].loc.1, 0);
+        }
+    }
+}
@@ -1,3 +1,468 @@
-use crate::line::Line;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::fs;
+use std::iter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::vec::IntoIter;
 
-pub fn prep(_lines: &mut Vec<Line>) {} // TODO
+use isa::lex;
+
+use crate::expr::{self, ExprError};
+use crate::line::{self, Line};
+use crate::VerboseError;
+
+/// Preprocessor, expanding directives within a translation unit.
+///
+/// | Directive         | Operation                                          |
+/// | ----------------- | -------------------------------------------------- |
+/// | `.define NAME ..` | Substitute `NAME` with the remaining tokens        |
+/// | `.include "path"` | Splice in the lines of another source file         |
+/// | `.if EXPR`        | Assemble the block if `EXPR` evaluates non-zero    |
+/// | `.ifdef NAME`     | Assemble the block if `NAME` is defined            |
+/// | `.ifndef NAME`    | Assemble the block if `NAME` is not defined        |
+/// | `.else`           | Assemble the block if the previous was skipped     |
+/// | `.endif`          | End a conditional block                            |
+/// | `.macro NAME ..`  | Define a macro taking comma separated parameters   |
+/// | `.endm`           | End a macro definition                             |
+///
+/// Macros are invoked by name, with their arguments substituted into the body.
+/// Each expansion is assembled within its own scope, so labels are local to it.
+#[derive(Debug, Default)]
+pub struct Prep<'a> {
+    incs: &'a [PathBuf],
+    defs: HashMap<String, Vec<String>>,
+    macros: HashMap<String, Macro>,
+    files: Vec<PathBuf>,
+    calls: Vec<String>,
+    errs: Vec<VerboseError>,
+}
+
+#[derive(Clone, Debug)]
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Line>,
+}
+
+#[derive(Debug)]
+struct Cond {
+    line: Line,
+    parent: bool,
+    active: bool,
+    other: bool,
+}
+
+impl<'a> Prep<'a> {
+    pub fn new(incs: &'a [PathBuf]) -> Self {
+        Self {
+            incs,
+            ..Default::default()
+        }
+    }
+
+    pub fn prep(&mut self, path: &Path, lines: Vec<Line>) -> Result<Vec<Line>, Vec<VerboseError>> {
+        let mut out = Vec::new();
+        self.files.push(canonicalize(path));
+        self.file(lines, &mut out);
+        self.files.pop();
+        match self.errs.is_empty() {
+            true => Ok(out),
+            false => Err(mem::take(&mut self.errs)),
+        }
+    }
+
+    fn file(&mut self, lines: Vec<Line>, out: &mut Vec<Line>) {
+        let mut conds: Vec<Cond> = Vec::new();
+        let mut iter = lines.into_iter();
+        while let Some(line) = iter.next() {
+            // Carry on past errors to report them all
+            if let Err(err) = self.line(line, &mut conds, &mut iter, out) {
+                self.errs.push(err);
+            }
+        }
+        // Ensure all conditionals were closed
+        if let Some(cond) = conds.pop() {
+            self.errs.push(PrepError::UnterminatedIf.at(&cond.line));
+        }
+    }
+
+    fn line(
+        &mut self,
+        mut line: Line,
+        conds: &mut Vec<Cond>,
+        iter: &mut IntoIter<Line>,
+        out: &mut Vec<Line>,
+    ) -> Result<(), VerboseError> {
+        let active = conds.last().is_none_or(|cond| cond.active);
+        let tokens: Vec<&str> = line.tokens.iter().map(String::as_str).collect();
+        match tokens[..] {
+            // Conditionals
+            [".", "if", ..] => {
+                let taken = match active {
+                    true => self.cond(&line.tokens[2..]),
+                    false => Ok(false),
+                };
+                match taken {
+                    Ok(taken) => conds.push(Cond::new(line, active, taken)),
+                    Err(err) => {
+                        // Open the block regardless, so its end is still matched
+                        let err = VerboseError::new(err.into(), &line);
+                        conds.push(Cond::new(line, active, false));
+                        return Err(err);
+                    }
+                }
+            }
+            [".", "ifdef", name] => {
+                let taken = active && self.defs.contains_key(name);
+                conds.push(Cond::new(line, active, taken));
+            }
+            [".", "ifndef", name] => {
+                let taken = active && !self.defs.contains_key(name);
+                conds.push(Cond::new(line, active, taken));
+            }
+            [".", "else"] => {
+                let cond = match conds.last_mut() {
+                    Some(cond) if !cond.other => cond,
+                    _ => return Err(PrepError::UnexpectedElse.at(&line)),
+                };
+                cond.other = true;
+                cond.active = cond.parent && !cond.active;
+            }
+            [".", "endif"] => {
+                conds
+                    .pop()
+                    .ok_or_else(|| PrepError::UnexpectedEndif.at(&line))?;
+            }
+            [".", "ifdef" | "ifndef" | "else" | "endif", ..] => {
+                return Err(PrepError::BadDirective.at(&line));
+            }
+            // Skip lines within inactive blocks
+            _ if !active => (),
+            // Definitions
+            [".", "define", name, ..] => {
+                let name = name.to_string();
+                let value = self.subst(&line.tokens[3..]);
+                self.defs.insert(name, value);
+            }
+            [".", "define", ..] => return Err(PrepError::BadDirective.at(&line)),
+            // Macros
+            [".", "macro", name, ..] => {
+                let name = name.to_string();
+                let mac = Macro::new(line, iter)?;
+                self.macros.insert(name, mac);
+            }
+            [".", "macro" | "endm", ..] => return Err(PrepError::BadDirective.at(&line)),
+            // Expand macros named in mnemonic position, leaving labels be
+            [name, ref rest @ ..] if rest != [":"] && self.macros.contains_key(name) => {
+                let name = name.to_string();
+                self.expand(&name, line, out)?;
+            }
+            // Inclusions
+            [".", "include", ..] => self.include(&line, out)?,
+            // Substitute definitions in all other lines
+            _ => {
+                line.tokens = self.subst(&line.tokens);
+                out.push(line);
+            }
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, line: &Line, out: &mut Vec<Line>) -> Result<(), VerboseError> {
+        // Extract the quoted path
+        let name = match line.text.split('"').collect::<Vec<_>>()[..] {
+            [_, name, rest] if lex::tokenize(rest).is_none() => name,
+            _ => return Err(PrepError::BadDirective.at(line)),
+        };
+        // Search relative to the including file, then the include paths
+        let path = line
+            .path
+            .parent()
+            .into_iter()
+            .chain(self.incs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+            .ok_or_else(|| PrepError::NotFound(name.to_string()).at(line))?;
+        // Ensure we are not already including this file
+        let canon = canonicalize(&path);
+        if self.files.contains(&canon) {
+            return Err(PrepError::Cycle(name.to_string()).at(line));
+        }
+        // Preprocess the included file in place
+        let lines = line::read(&path).map_err(|err| VerboseError::new(err.into(), line))?;
+        self.files.push(canon);
+        self.file(lines, out);
+        self.files.pop();
+        Ok(())
+    }
+
+    fn expand(&mut self, name: &str, line: Line, out: &mut Vec<Line>) -> Result<(), VerboseError> {
+        let mac = self.macros[name].clone();
+        // Split arguments at separators
+        let args: Vec<&[String]> = match line.tokens.len() {
+            1 => Vec::new(),
+            _ => line.tokens[1..].split(|token| token == ",").collect(),
+        };
+        if args.len() != mac.params.len() || args.iter().any(|arg| arg.is_empty()) {
+            return Err(PrepError::BadArgs(mac.params.len()).at(&line));
+        }
+        // Guard against unbounded recursion
+        if self.calls.iter().any(|call| call == name) {
+            return Err(PrepError::Recursive(name.to_string()).at(&line));
+        }
+        // Substitute arguments into a fresh scope
+        let site = Box::new(line.clone());
+        let scope = |tokens: &[&str]| Line {
+            tokens: tokens.iter().map(|token| token.to_string()).collect(),
+            ..line.clone()
+        };
+        let body = iter::once(scope(&[".", "begin"]))
+            .chain(mac.body.into_iter().map(|mut body| {
+                body.tokens = body
+                    .tokens
+                    .iter()
+                    .flat_map(|token| match mac.params.iter().position(|p| p == token) {
+                        Some(idx) => args[idx].to_vec(),
+                        None => vec![token.clone()],
+                    })
+                    .collect();
+                body.site = Some(site.clone());
+                body
+            }))
+            .chain(iter::once(scope(&[".", "end"])))
+            .collect();
+        // Preprocess the expansion in place
+        self.calls.push(name.to_string());
+        self.file(body, out);
+        self.calls.pop();
+        Ok(())
+    }
+
+    fn cond(&self, tokens: &[String]) -> Result<bool, ExprError> {
+        Ok(expr::eval(&self.subst(tokens), |_| None)? != 0)
+    }
+
+    fn subst(&self, tokens: &[String]) -> Vec<String> {
+        tokens
+            .iter()
+            .flat_map(|token| match self.defs.get(token) {
+                Some(value) => value.clone(),
+                None => vec![token.clone()],
+            })
+            .collect()
+    }
+}
+
+impl Macro {
+    fn new(line: Line, iter: &mut IntoIter<Line>) -> Result<Self, VerboseError> {
+        // Parse parameters
+        let params: Vec<String> = match line.tokens.len() {
+            3 => Vec::new(),
+            _ => line.tokens[3..]
+                .split(|token| token == ",")
+                .map(|param| match param {
+                    [param] => Ok(param.clone()),
+                    _ => Err(PrepError::BadDirective.at(&line)),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        // Collect the body
+        let mut body = Vec::new();
+        for next in iter.by_ref() {
+            match next.tokens.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                [".", "endm"] => return Ok(Self { params, body }),
+                [".", "macro", ..] => return Err(PrepError::BadDirective.at(&next)),
+                _ => body.push(next),
+            }
+        }
+        Err(PrepError::UnterminatedMacro.at(&line))
+    }
+}
+
+impl Cond {
+    fn new(line: Line, parent: bool, taken: bool) -> Self {
+        Self {
+            line,
+            parent,
+            active: parent && taken,
+            other: false,
+        }
+    }
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[derive(Debug)]
+pub enum PrepError {
+    BadDirective,
+    UnexpectedElse,
+    UnexpectedEndif,
+    UnterminatedIf,
+    NotFound(String),
+    Cycle(String),
+    UnterminatedMacro,
+    BadArgs(usize),
+    Recursive(String),
+}
+
+impl PrepError {
+    fn at(self, line: &Line) -> VerboseError {
+        VerboseError::new(self.into(), line)
+    }
+}
+
+impl Display for PrepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::BadDirective => "Malformed preprocessor directive".to_string(),
+                Self::UnexpectedElse => "Found `.else` without matching `.if`".to_string(),
+                Self::UnexpectedEndif => "Found `.endif` without matching `.if`".to_string(),
+                Self::UnterminatedIf => "Missing `.endif` for conditional".to_string(),
+                Self::NotFound(path) => format!("Could not find included file `{}`", path),
+                Self::Cycle(path) => format!("Cyclic inclusion of `{}`", path),
+                Self::UnterminatedMacro => "Missing `.endm` for macro".to_string(),
+                Self::BadArgs(count) => format!("Expected {} macro arguments", count),
+                Self::Recursive(name) => format!("Recursive invocation of macro `{}`", name),
+            }
+        )
+    }
+}
+
+impl Error for PrepError {}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::test_util::lines;
+
+    fn text(lines: &[Line]) -> Vec<String> {
+        lines.iter().map(|line| line.tokens.join(" ")).collect()
+    }
+
+    #[test]
+    fn cond() {
+        let path = Path::new("cond.s");
+        let src = "
+            .define ONE 0d1
+            .define REG r4
+            .if (ONE << 0d2) - 0d4 | ONE
+            mov REG, ONE
+            .ifdef TWO
+            hlt
+            .else
+            add REG, REG
+            .endif
+            .else
+            sys
+            .endif
+            .ifndef ONE
+            sys
+            .endif
+        ";
+        let out = Prep::new(&[]).prep(path, lines(path, src)).unwrap();
+        assert_eq!(text(&out), ["mov r4 , 0d1", "add r4 , r4"]);
+        // Unbalanced conditionals are reported against their line
+        let err = Prep::new(&[])
+            .prep(path, lines(path, "\n.ifdef ONE\n"))
+            .unwrap_err();
+        assert_eq!(err[0].loc, (path.to_path_buf(), 1));
+        assert!(Prep::new(&[]).prep(path, lines(path, ".endif")).is_err());
+        // Each malformed line is reported
+        let err = Prep::new(&[])
+            .prep(path, lines(path, ".endif\nhlt\n.else\n.define"))
+            .unwrap_err();
+        assert_eq!(err.len(), 3);
+        assert!(Prep::new(&[])
+            .prep(path, lines(path, ".if r4\n.endif"))
+            .is_err());
+    }
+
+    #[test]
+    fn macros() {
+        let path = Path::new("macro.s");
+        let src = "
+            .macro save reg
+            push reg
+            .endm
+            .macro skip reg, val
+            save reg
+            goto done
+            mov reg, val
+            done:
+            .endm
+            skip r4, 0d1
+            skip r5, 0d2
+        ";
+        let out = Prep::new(&[]).prep(path, lines(path, src)).unwrap();
+        assert_eq!(
+            text(&out[..7]),
+            [
+                ". begin",
+                ". begin",
+                "push r4",
+                ". end",
+                "goto done",
+                "mov r4 , 0d1",
+                "done :"
+            ]
+        );
+        assert_eq!(out.len(), 16);
+        // Expanded lines point back to their call sites
+        assert_eq!(out[2].number, 2);
+        assert_eq!(out[2].site.as_ref().unwrap().number, 5);
+        assert_eq!(
+            out[2].site.as_ref().unwrap().site.as_ref().unwrap().number,
+            10
+        );
+        // Invocations must match the definition
+        let src = ".macro nop\nmov r0, r0\n.endm\nnop r1";
+        assert!(Prep::new(&[]).prep(path, lines(path, src)).is_err());
+        let src = ".macro loop\ngoto loop\n.endm\nloop:\nloop";
+        let out = Prep::new(&[]).prep(path, lines(path, src)).unwrap();
+        assert_eq!(text(&out), ["loop :", ". begin", "goto loop", ". end"]);
+        let src = ".macro loop\nloop\n.endm\nloop";
+        let err = Prep::new(&[]).prep(path, lines(path, src)).unwrap_err();
+        assert_eq!(err[0].loc, (path.to_path_buf(), 1));
+        assert_eq!(err[0].notes.len(), 1);
+        assert!(Prep::new(&[])
+            .prep(path, lines(path, ".macro nop\n"))
+            .is_err());
+    }
+
+    #[test]
+    fn include() {
+        let dir = env::temp_dir().join(format!("asm-prep-{}", std::process::id()));
+        let inc = dir.join("inc");
+        fs::create_dir_all(&inc).unwrap();
+        fs::write(dir.join("main.s"), ".include \"lib.s\"\nmov r0, N\n").unwrap();
+        fs::write(inc.join("lib.s"), ".define N 0d7\nhlt\n").unwrap();
+        fs::write(dir.join("loop.s"), ".include \"loop.s\"\n").unwrap();
+        // Included files are found using the include paths
+        let path = dir.join("main.s");
+        assert!(Prep::new(&[])
+            .prep(&path, line::read(&path).unwrap())
+            .is_err());
+        let incs = [inc.clone()];
+        let out = Prep::new(&incs)
+            .prep(&path, line::read(&path).unwrap())
+            .unwrap();
+        assert_eq!(text(&out), ["hlt", "mov r0 , 0d7"]);
+        assert_eq!(out[0].path, inc.join("lib.s"));
+        // Cycles are detected
+        let path = dir.join("loop.s");
+        let err = Prep::new(&[]).prep(&path, line::read(&path).unwrap());
+        assert!(matches!(
+            err.as_deref().map_err(Vec::as_slice),
+            Err([VerboseError { loc: (_, 0), .. }])
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,28 +1,62 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::mem;
 use std::vec::IntoIter;
 
+use isa::{iarch, lex, Cond};
+
+use crate::data;
+use crate::expr::{self, ExprError};
 use crate::line::{Line, Source};
-use crate::{WORDSIZE, iarch};
+use crate::obj::{Bind, Kind, Reloc, Symbol, Target};
+use crate::pool::{self, Pool};
+use crate::{uarch, VerboseError, WORDSIZE};
+
+/// Index of the stack pointer.
+const SP: uarch = 13;
+/// Index of the program counter.
+const PC: uarch = 15;
 
 #[derive(Clone, Debug, Default)]
 pub struct Scope {
     pub source: Vec<Source>,
     pub symbols: HashMap<String, usize>,
+    pub sites: HashMap<String, Line>,
+    pub globals: HashMap<String, Line>,
+    pub externs: HashMap<String, Line>,
+    pub entries: Vec<(usize, Line)>,
+    /// Whether this scope is a function body, introduced by `.func`
+    pub func: bool,
 }
 
 impl Scope {
-    pub fn new(lines: Vec<Line>) -> Self {
+    pub fn new(lines: Vec<Line>) -> Result<Self, VerboseError> {
         let mut scope = Self::default();
         // Construct using an IntoIter
-        scope.ctor(&mut lines.into_iter());
+        scope.ctor(&mut lines.into_iter(), &HashMap::new())?;
         // Ensure we finished the file
         // TODO
+        // Ensure exported symbols are defined
+        for (symbol, line) in &scope.globals {
+            if !scope.symbols.contains_key(symbol) {
+                return Err(VerboseError::new(
+                    ExprError::Undefined(symbol.clone()).into(),
+                    line,
+                ));
+            }
+        }
         // Return the constructed scope
-        scope
+        Ok(scope)
     }
 
-    fn ctor(&mut self, iter: &mut IntoIter<Line>) {
+    fn ctor(
+        &mut self,
+        iter: &mut IntoIter<Line>,
+        regs: &HashMap<String, String>,
+    ) -> Result<(), VerboseError> {
+        // Register aliases last until the end of the scope
+        let mut regs = regs.clone();
         // Construct from lines iterator
         while let Some(line) = iter.next() {
             match line
@@ -31,27 +65,203 @@ impl Scope {
                 .map(String::as_str)
                 .collect::<Vec<&str>>()[..]
             {
-                [".", "begin" | "func"] => {
-                    let mut scope = Self::default();
-                    scope.ctor(iter);
-                    self.source.push(Source::Scope(scope));
+                [".", "end"] => return Ok(()),
+                [symbol, ":"] => self.label(symbol, &line)?,
+                [".", "func"] => {
+                    // Name a function labelled after `.func` in this scope
+                    let name = iter
+                        .as_slice()
+                        .first()
+                        .filter(|next| matches!(&next.tokens[..], [_, colon] if colon == ":"));
+                    if let Some(next) = name.cloned() {
+                        self.label(&next.tokens[0], &next)?;
+                        iter.next();
+                    }
+                    let src = Self::source(line, iter, &regs)?;
+                    self.source.push(src);
+                }
+                [".", kind @ ("global" | "extern"), ref names @ ..] => {
+                    // Declare the visibility of each symbol
+                    let names = symbols(names).ok_or_else(|| ScopeError::BadSymbols.at(&line))?;
+                    let decls = match kind {
+                        "global" => &mut self.globals,
+                        _ => &mut self.externs,
+                    };
+                    for name in names {
+                        decls
+                            .entry(name.to_string())
+                            .or_insert_with(|| line.clone());
+                    }
+                }
+                [".", "reg", name, ",", reg] => {
+                    // Aliases may not shadow a register
+                    let reg = regs.get(reg).map_or(reg, String::as_str);
+                    match (lex::parse_reg(name), lex::parse_reg(&reg.to_lowercase())) {
+                        (Err(_), Ok(_)) => regs.insert(name.to_string(), reg.to_string()),
+                        _ => return Err(ScopeError::BadAlias.at(&line)),
+                    };
+                }
+                [".", "reg", ..] => return Err(ScopeError::BadAlias.at(&line)),
+                [".", "entry"] => self.entries.push((self.source.len(), line)),
+                _ => {
+                    let src = Self::source(line, iter, &regs)?;
+                    self.source.push(src);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn label(&mut self, symbol: &str, line: &Line) -> Result<(), VerboseError> {
+        // Labels may only be defined once per scope
+        if let Some(prev) = self.sites.get(symbol) {
+            return Err(ScopeError::Duplicate(symbol.to_string())
+                .at(line)
+                .note("previously defined here", prev));
+        }
+        self.symbols.insert(symbol.to_string(), self.source.len());
+        self.sites.insert(symbol.to_string(), line.clone());
+        Ok(())
+    }
+
+    fn source(
+        mut line: Line,
+        iter: &mut IntoIter<Line>,
+        regs: &HashMap<String, String>,
+    ) -> Result<Source, VerboseError> {
+        let tokens: Vec<&str> = line.tokens.iter().map(String::as_str).collect();
+        match tokens[..] {
+            [".", kind @ ("begin" | "func")] => {
+                let mut scope = Self {
+                    func: kind == "func",
+                    ..Default::default()
+                };
+                scope.ctor(iter, regs)?;
+                // Visibility may only be declared at file scope
+                if let Some(decl) = scope.globals.values().chain(scope.externs.values()).next() {
+                    return Err(ScopeError::Nested.at(decl));
                 }
-                [".", "end"] => return,
-                [symbol, ":"] => {
-                    self.symbols
-                        .insert(symbol.to_string(), self.source.len());
+                Ok(Source::Scope(scope))
+            }
+            [".", "repeat", count] => {
+                // Parse the repetition count
+                let count = lex::parse_imm(count)
+                    .map(usize::from)
+                    .or_else(|_| count.parse())
+                    .map_err(|_| ScopeError::BadCount.at(&line))?;
+                // Construct the repeated source
+                let next = iter
+                    .next()
+                    .filter(|next| {
+                        let tokens: Vec<&str> = next.tokens.iter().map(String::as_str).collect();
+                        !matches!(tokens[..], [".", "end"] | [_, ":"])
+                    })
+                    .ok_or_else(|| ScopeError::MissingBody.at(&line))?;
+                let src = Self::source(next, iter, regs)?;
+                // Give each copy its own scope
+                Ok(Source::Scope(Self {
+                    source: vec![src; count],
+                    ..Default::default()
+                }))
+            }
+            [".", "repeat", ..] => Err(ScopeError::BadCount.at(&line)),
+            [".", ..] => Ok(Source::Line(data::parse(line)?)),
+            _ => {
+                // Substitute register aliases into operands
+                for token in line.tokens.iter_mut().skip(1) {
+                    if let Some(reg) = regs.get(token) {
+                        *token = reg.clone();
+                    }
                 }
-                _ => self.source.push(Source::Line(line)),
+                Ok(Source::Line(line))
             }
         }
     }
 
-    pub fn subst(&mut self) {
-        // Perform substitution in 2 passes (descending the scope tree):
-        // 1. update symbol addresses
-        self.update(&mut 0);
-        // 2. replace symbol occurences
-        self.replace(&mut 0, &HashMap::new());
+    /// Substitutes symbols and evaluates operands, returning relocations
+    /// along with any warnings, or else all diagnostics.
+    pub fn subst(
+        &mut self,
+        strict: bool,
+    ) -> Result<(Vec<Reloc>, Vec<VerboseError>), Vec<VerboseError>> {
+        // Lay out a copy of the scope, expanding lines whose targets or
+        // immediates are out of range until addresses converge
+        loop {
+            let mut scope = self.clone();
+            let mut layout = scope.layout(strict);
+            if layout.far.is_empty() || !layout.errs.is_empty() {
+                // Report each diagnostic once, as repetitions share their lines
+                let mut seen = HashSet::new();
+                let mut once =
+                    |diag: &VerboseError| seen.insert((diag.loc.clone(), diag.to_string()));
+                layout.errs.retain(&mut once);
+                layout.warnings.retain(&mut once);
+                layout.warnings.sort_by(|a, b| a.loc.cmp(&b.loc));
+                if !layout.errs.is_empty() {
+                    layout.errs.extend(layout.warnings);
+                    return Err(layout.errs);
+                }
+                *self = scope;
+                return Ok((layout.relocs, layout.warnings));
+            }
+            let mut errs = Vec::new();
+            self.relax(&layout.far, &mut 0, &mut errs);
+            if !errs.is_empty() {
+                return Err(errs);
+            }
+        }
+    }
+
+    fn layout(&mut self, strict: bool) -> Layout {
+        let mut pool = Pool::default();
+        let mut layout = Layout {
+            strict,
+            ..Default::default()
+        };
+        // Perform substitution in 3 passes (descending the scope tree):
+        // 1. place literal pools
+        self.place(&mut 0, &mut pool);
+        self.source.extend(pool.finish().map(Source::Line));
+        // 2. update symbol addresses
+        self.update(&mut 0, &mut pool);
+        layout.entries = self.entries().into_iter().map(|(addr, _)| addr).collect();
+        // 3. evaluate operands, resolving symbol occurences
+        let externs = self.externs.clone();
+        self.replace(&mut 0, &HashMap::new(), &externs, &mut pool, &mut layout);
+        // Warn of external symbols never referred to
+        for (name, line) in &self.externs {
+            if !layout.used.contains(name) {
+                let warning = ScopeError::UnusedExtern(name.clone()).at(line).warning();
+                layout.warnings.push(warning);
+            }
+        }
+        layout
+    }
+
+    /// Collects the labels of this scope and those nested within it, which
+    /// are local unless declared global.
+    pub fn labels(&self) -> Vec<Symbol> {
+        let mut labels: Vec<_> = self
+            .symbols
+            .iter()
+            .map(|(name, &value)| Symbol {
+                name: name.clone(),
+                bind: match self.globals.contains_key(name) {
+                    true => Bind::Global,
+                    false => Bind::Local,
+                },
+                section: Some(0),
+                value,
+                path: self.sites[name].path.display().to_string(),
+                line: self.sites[name].number,
+            })
+            .collect();
+        for src in &self.source {
+            if let Source::Scope(scope) = src {
+                labels.extend(scope.labels());
+            }
+        }
+        labels
     }
 
     pub fn flatten(self) -> Vec<Line> {
@@ -64,42 +274,1080 @@ impl Scope {
             .collect()
     }
 
-    fn update(&mut self, count: &mut usize) {
-        self.symbols.iter_mut().for_each(|(_, v)| *v += *count);
+    /// Collects the address of each entry point declared within this scope or
+    /// those nested within it.
+    pub fn entries(&self) -> Vec<(usize, &Line)> {
+        let mut entries: Vec<_> = self.entries.iter().map(|(v, line)| (*v, line)).collect();
+        for src in &self.source {
+            if let Source::Scope(scope) = src {
+                entries.extend(scope.entries());
+            }
+        }
+        entries
+    }
+
+    fn place(&mut self, addr: &mut usize, pool: &mut Pool) {
+        let mut idx = 0;
+        while idx < self.source.len() {
+            match &mut self.source[idx] {
+                Source::Line(line) => {
+                    // Explicit pools take all pending literals
+                    if line.tokens == [".", "pool"] {
+                        *line = pool.flush(line, false);
+                    }
+                    // Place a pool early if this line would take pending
+                    // literals out of range
+                    if !pool.reaches(*addr + data::size(&line.tokens, *addr)) {
+                        // Never separate an `if` from the line it predicates
+                        let at = match idx.checked_sub(1).map(|prev| &self.source[prev]) {
+                            Some(Source::Line(prev)) if predicate(&prev.tokens).is_some() => {
+                                *addr -= WORDSIZE;
+                                idx - 1
+                            }
+                            _ => idx,
+                        };
+                        let Source::Line(line) = &self.source[at] else {
+                            unreachable!()
+                        };
+                        let early = pool.flush(line, true);
+                        *addr += data::size(&early.tokens, *addr);
+                        self.source.insert(at, Source::Line(early));
+                        // Symbols remain with the displaced line
+                        self.displace(at, 1);
+                        idx = at + 1;
+                        continue;
+                    }
+                    if pool::literal(&line.tokens) {
+                        pool.push(*addr, line);
+                    }
+                    *addr += data::size(&line.tokens, *addr);
+                }
+                Source::Scope(scope) => scope.place(addr, pool),
+            }
+            idx += 1;
+        }
+    }
+
+    fn update(&mut self, count: &mut usize, pool: &mut Pool) {
+        // Record the address of each source
+        let mut addrs = Vec::with_capacity(self.source.len() + 1);
         for src in self.source.iter_mut() {
+            addrs.push(*count);
             match src {
-                Source::Line(_) => *count += 1,
-                Source::Scope(scope) => {
-                    scope.update(count);
-                    for (_, v) in self.symbols.iter_mut() {
-                        if *v > *count {
-                            *v += *count;
-                        }
-                    }
+                Source::Line(line) => {
+                    pool.update(&line.tokens, *count);
+                    *count += data::size(&line.tokens, *count);
                 }
+                Source::Scope(scope) => scope.update(count, pool),
             }
         }
+        addrs.push(*count);
+        // Resolve symbols to the address of their source
+        self.symbols
+            .values_mut()
+            .chain(self.entries.iter_mut().map(|(v, _)| v))
+            .for_each(|v| *v = addrs[*v]);
     }
 
-    fn replace(&mut self, idx: &mut usize, symbols: &HashMap<String, usize>) {
-        self.symbols.extend(symbols.clone());
+    fn replace(
+        &mut self,
+        idx: &mut usize,
+        symbols: &HashMap<String, usize>,
+        externs: &HashMap<String, Line>,
+        pool: &mut Pool,
+        layout: &mut Layout,
+    ) {
+        // Inner symbols shadow outer ones
+        let mut symbols = symbols.clone();
+        symbols.extend(self.symbols.clone());
+        let outer = mem::take(&mut layout.used);
+        let quiet = layout.quiet;
+        layout.quiet |= self.func;
+        let mut funcs = HashSet::new();
         for src in self.source.iter_mut() {
             match src {
                 Source::Line(line) => {
-                    for token in line
-                        .tokens
-                        .iter_mut()
-                        .filter(|t| self.symbols.contains_key(*t))
-                    {
-                        let symbol = self.symbols[token] as iarch;
-                        let delta = symbol - (*idx as iarch + 1);
-                        let delta = (WORDSIZE as iarch).saturating_mul(delta);
-                        mem::swap(token, &mut format!("{:#x}", delta));
+                    // Note which symbols are referred to
+                    let used = line.tokens.iter().skip(1).filter(|token| {
+                        symbols.contains_key(*token) || externs.contains_key(*token)
+                    });
+                    layout.used.extend(used.cloned());
+                    let relocs = &mut layout.relocs;
+                    let strict = layout.strict;
+                    match resolve(
+                        &mut line.tokens,
+                        *idx,
+                        &symbols,
+                        externs,
+                        pool,
+                        relocs,
+                        strict,
+                    ) {
+                        // Out of range targets are relaxed in the next layout
+                        Err(err) if matches!(err.downcast_ref(), Some(ScopeError::OutOfRange)) => {
+                            layout.far.push(layout.lines)
+                        }
+                        // Carry on past errors to report them all
+                        Err(err) => layout.errs.push(VerboseError::new(err, line)),
+                        Ok(()) => (),
+                    }
+                    // Early pools are not part of the source
+                    if !pool::early(&line.tokens) {
+                        layout.lines += 1;
+                    }
+                    *idx += data::size(&line.tokens, *idx);
+                }
+                Source::Scope(scope) => {
+                    if scope.func {
+                        funcs.insert(*idx);
                     }
-                    *idx += 1;
+                    scope.replace(idx, &symbols, externs, pool, layout)
+                }
+            }
+        }
+        // Warn of labels never referred to, unless exported, an entry point,
+        // naming a function, trailing, or within a function body
+        for (name, line) in &self.sites {
+            let addr = self.symbols[name];
+            let named = layout.entries.contains(&addr) || funcs.contains(&addr) || addr == *idx;
+            if !layout.quiet
+                && !named
+                && !layout.used.contains(name)
+                && !self.globals.contains_key(name)
+            {
+                let warning = ScopeError::Unused(name.clone()).at(line).warning();
+                layout.warnings.push(warning);
+            }
+        }
+        layout.quiet = quiet;
+        // Labels of this scope are hidden from those enclosing it
+        layout.used.retain(|name| !self.symbols.contains_key(name));
+        layout.used.extend(outer);
+    }
+
+    /// Lowers conditional branches, which the Huffman encoding lacks, into an
+    /// `if` predicating an unconditional branch.
+    pub fn lower(&mut self, errs: &mut Vec<VerboseError>) {
+        let mut idx = 0;
+        while idx < self.source.len() {
+            let line = match &mut self.source[idx] {
+                Source::Line(line) => line.clone(),
+                Source::Scope(scope) => {
+                    scope.lower(errs);
+                    idx += 1;
+                    continue;
+                }
+            };
+            let mnemonic = line.tokens[0].to_lowercase();
+            let (kind, cond) = match mnemonic.split_at_checked(4) {
+                Some((kind @ ("goto" | "call"), cond))
+                    if !matches!(cond, "" | "al") && cond.parse::<Cond>().is_ok() =>
+                {
+                    (kind, cond)
+                }
+                _ => {
+                    idx += 1;
+                    continue;
+                }
+            };
+            // An `if` cannot itself be predicated
+            if let Some(Source::Line(prev)) = idx.checked_sub(1).map(|prev| &self.source[prev]) {
+                if predicate(&prev.tokens).is_some() {
+                    errs.push(ScopeError::Predicated.at(&line));
+                    idx += 1;
+                    continue;
                 }
-                Source::Scope(scope) => scope.replace(idx, &self.symbols),
             }
+            let iff = Line {
+                tokens: vec![format!("if{}", cond)],
+                ..line.clone()
+            };
+            let bra = Line {
+                tokens: [vec![kind.to_string()], line.tokens[1..].to_vec()].concat(),
+                ..line
+            };
+            self.source
+                .splice(idx..=idx, [Source::Line(iff), Source::Line(bra)]);
+            // Symbols following the line move past its branch
+            self.displace(idx + 1, 1);
+            idx += 2;
         }
     }
+
+    /// Relaxes the source lines at each ordinal in `far`, counted in order
+    /// from `count`, into sequences that reach their targets.
+    fn relax(&mut self, far: &[usize], count: &mut usize, errs: &mut Vec<VerboseError>) {
+        let mut idx = 0;
+        while idx < self.source.len() {
+            let line = match &mut self.source[idx] {
+                Source::Line(line) => line.clone(),
+                Source::Scope(scope) => {
+                    scope.relax(far, count, errs);
+                    idx += 1;
+                    continue;
+                }
+            };
+            *count += 1;
+            if !far.contains(&(*count - 1)) {
+                idx += 1;
+                continue;
+            }
+            let prev = match idx.checked_sub(1).map(|prev| &self.source[prev]) {
+                Some(Source::Line(prev)) => Some(prev),
+                _ => None,
+            };
+            let (pred, seq) = match expand(&line, prev) {
+                Ok(expansion) => expansion,
+                Err(err) => {
+                    errs.push(err);
+                    idx += 1;
+                    continue;
+                }
+            };
+            if let Some(pred) = pred {
+                self.source[idx - 1] = Source::Line(pred);
+            }
+            // Symbols following the line move past its sequence
+            let len = seq.len();
+            self.source
+                .splice(idx..=idx, seq.into_iter().map(Source::Line));
+            self.displace(idx + 1, len - 1);
+            idx += len;
+        }
+    }
+
+    /// Moves symbols and entries at or after source `idx` by `by` sources.
+    fn displace(&mut self, idx: usize, by: usize) {
+        self.symbols
+            .values_mut()
+            .chain(self.entries.iter_mut().map(|(v, _)| v))
+            .filter(|v| **v >= idx)
+            .for_each(|v| *v += by);
+    }
+}
+
+/// Results of laying out a scope tree.
+#[derive(Debug, Default)]
+struct Layout {
+    /// Whether wide immediates are rejected rather than synthesized
+    strict: bool,
+    relocs: Vec<Reloc>,
+    /// Ordinals of source lines whose targets are out of range
+    far: Vec<usize>,
+    lines: usize,
+    /// Symbols referred to within the scope being laid out
+    used: HashSet<String>,
+    /// Addresses of declared entry points
+    entries: Vec<usize>,
+    /// Whether unused labels go unreported, as within function bodies
+    quiet: bool,
+    errs: Vec<VerboseError>,
+    warnings: Vec<VerboseError>,
+}
+
+/// Expands a line whose target or immediate is out of range into a longer
+/// sequence, along with a replacement for the `if` predicating it (as it
+/// covers a single instruction).
+///
+/// PC-relative branches and loads take the address of their target from a
+/// literal pool, whereas wide immediates are synthesized.
+fn expand(line: &Line, prev: Option<&Line>) -> Result<(Option<Line>, Vec<Line>), VerboseError> {
+    let mnemonic = line.tokens[0].to_lowercase();
+    let (mut seq, cond) = match (mnemonic.split_at_checked(4), &line.tokens[..]) {
+        (Some((kind @ ("goto" | "call"), cond)), [_, target @ ..]) => {
+            let mut seq = Vec::new();
+            if kind == "call" {
+                // Return past the load of the target
+                seq.push(words("mov lr , pc"));
+                seq.push(words("add lr , 0x4"));
+            }
+            seq.push([words("ldr pc , ="), target.to_vec()].concat());
+            (seq, cond)
+        }
+        (_, [_, reg, _, addr @ ..]) if mnemonic == "ldr" => {
+            let load = [words(&format!("ldr {} , =", reg)), addr.to_vec()].concat();
+            (vec![load, words(&format!("ldr {0} , {0}", reg))], "")
+        }
+        (_, [_, reg, _, imm @ ..]) if fits(&mnemonic, 0).is_some() => {
+            let seq = synth(&mnemonic, reg, imm).ok_or_else(|| ScopeError::Wide.at(line))?;
+            (seq, "")
+        }
+        // Stores have no register free to hold the address
+        _ => return Err(ScopeError::OutOfRange.at(line)),
+    };
+    let skip = |seq: &[Vec<String>]| format!("{:#x}", seq.len() * WORDSIZE);
+    // Branch over the sequence unless the condition holds
+    if !matches!(cond, "" | "al") {
+        let inv = invert(cond).ok_or_else(|| ScopeError::OutOfRange.at(line))?;
+        seq.insert(0, words(&format!("goto{} {}", inv, skip(&seq))));
+    }
+    let mut pred = None;
+    if let Some(prev) = prev.filter(|_| seq.len() > 1) {
+        let cond = predicate(&prev.tokens);
+        // Branch over the sequence unless the predicate holds
+        if let Some(inv) = cond.and_then(|cond| invert(&cond)) {
+            pred = Some(Line {
+                tokens: vec![format!("if{}", inv)],
+                ..prev.clone()
+            });
+            seq.insert(0, words(&format!("goto {}", skip(&seq))));
+        }
+    }
+    let seq = seq
+        .into_iter()
+        .map(|tokens| Line {
+            tokens,
+            ..line.clone()
+        })
+        .collect();
+    Ok((pred, seq))
+}
+
+/// Finds the condition of an `if`, which predicates the line following it.
+fn predicate(tokens: &[String]) -> Option<String> {
+    match tokens {
+        [iff] => iff.to_lowercase().strip_prefix("if").map(str::to_string),
+        _ => None,
+    }
+}
+
+/// Synthesizes an instruction with a wide immediate, building the immediate
+/// in its destination or in a scratch register saved on the stack.
+///
+/// Moves leave the condition code flags untouched, so are always loaded from
+/// the pool rather than built by a chain of flag-setting instructions.
+fn synth(mnemonic: &str, reg: &str, imm: &[String]) -> Option<Vec<Vec<String>>> {
+    let op1 = lex::parse_reg(&reg.to_lowercase()).ok()?;
+    let moves = matches!(mnemonic, "mov" | "neg" | "not");
+    let build = |dst: &str, imm: Vec<String>| {
+        let load = [words(&format!("ldr {} , =", dst)), imm.clone()].concat();
+        // Constants may be built without the pool when no longer than a load
+        // and its entry, whereas expressions of symbols are always loaded as
+        // their value may yet change
+        match expr::eval(&imm, |_| None) {
+            Ok(value) if !moves && lex::parse_reg(&dst.to_lowercase()).ok() != Some(PC) => {
+                chain(value as uarch, 2)
+                    .map(|seq| {
+                        seq.into_iter()
+                            .map(|(op, imm)| words(&format!("{} {} , {:#x}", op, dst, imm)))
+                            .collect()
+                    })
+                    .unwrap_or_else(|| vec![load])
+            }
+            _ => vec![load],
+        }
+    };
+    // Negations are folded into the immediate
+    let unary = |op: &str| [words(&format!("{} (", op)), imm.to_vec(), words(")")].concat();
+    Some(match mnemonic {
+        "mov" => build(reg, imm.to_vec()),
+        "neg" => build(reg, unary("-")),
+        "not" => build(reg, unary("~")),
+        // The stack pointer and PC cannot be used while saving a register
+        _ if op1 == SP || op1 == PC => return None,
+        _ => {
+            let tmp = if op1 == 0 { "r1" } else { "r0" };
+            [
+                vec![words(&format!("push {}", tmp))],
+                build(tmp, imm.to_vec()),
+                vec![words(&format!("{} {} , {}", mnemonic, reg, tmp))],
+                vec![words(&format!("pop {}", tmp))],
+            ]
+            .concat()
+        }
+    })
+}
+
+/// Finds the shortest sequence of at most `limit` instructions building
+/// `value` in a register, starting from a `mov`.
+fn chain(value: uarch, limit: usize) -> Option<Vec<(&'static str, uarch)>> {
+    // Search breadth-first, recording the step reaching each value
+    let mut steps: HashMap<uarch, (Option<uarch>, &str, uarch)> = HashMap::new();
+    let mut frontier: Vec<uarch> = (0..0x40)
+        .chain(-0x40..0)
+        .map(|imm: iarch| imm as uarch)
+        .collect();
+    for &imm in &frontier {
+        steps.insert(imm, (None, "mov", imm));
+    }
+    for _ in 1..limit {
+        if steps.contains_key(&value) {
+            break;
+        }
+        let mut next = Vec::new();
+        for prev in frontier {
+            let shifts = (1..0x10).map(|imm| ("lsl", imm, prev << imm));
+            let ors = (-0x40..0x40).map(|imm: iarch| ("orr", imm as uarch, prev | imm as uarch));
+            let adds = (0..0x80).map(|imm| ("add", imm, prev.wrapping_add(imm)));
+            let subs = (0..0x80).map(|imm| ("sub", imm, prev.wrapping_sub(imm)));
+            for (op, imm, res) in shifts.chain(ors).chain(adds).chain(subs) {
+                steps.entry(res).or_insert_with(|| {
+                    next.push(res);
+                    (Some(prev), op, imm)
+                });
+            }
+        }
+        frontier = next;
+    }
+    // Walk back from the value to the initial `mov`
+    let mut seq = Vec::new();
+    let mut at = Some(value);
+    while let Some(value) = at {
+        let &(prev, op, imm) = steps.get(&value)?;
+        seq.push((op, imm));
+        at = prev;
+    }
+    seq.reverse();
+    Some(seq)
+}
+
+/// Checks if an immediate fits the encoding of an instruction, as extended
+/// when executed, if the instruction operates on immediate data.
+fn fits(mnemonic: &str, value: i32) -> Option<bool> {
+    let value = value as uarch;
+    Some(match mnemonic.to_lowercase().as_str() {
+        "add" | "sub" | "rsb" => value < 0x80,
+        "lsr" | "asr" | "ror" | "lsl" | "asl" | "rol" => value < 0x10,
+        "mov" | "neg" | "not" | "and" | "orr" | "xor" | "mul" | "cmp" | "cmn" | "tst" | "teq" => {
+            (-0x40..0x40).contains(&(value as iarch))
+        }
+        _ => return None,
+    })
+}
+
+/// Splits a line of tokens separated by whitespace.
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(String::from).collect()
+}
+
+/// Finds the inverse of a condition code.
+fn invert(cond: &str) -> Option<Cond> {
+    Some(cond.parse::<Cond>().ok()?.invert())
+}
+
+/// Parses a comma-separated list of symbols.
+fn symbols<'a>(tokens: &[&'a str]) -> Option<Vec<&'a str>> {
+    tokens
+        .split(|&token| token == ",")
+        .map(|symbol| match symbol {
+            [symbol] if symbol.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                Some(*symbol)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Evaluates the operands of a line placed at `addr`, recording relocations
+/// for any that depend on the final placement of symbols.
+fn resolve(
+    tokens: &mut Vec<String>,
+    addr: usize,
+    symbols: &HashMap<String, usize>,
+    externs: &HashMap<String, Line>,
+    pool: &mut Pool,
+    relocs: &mut Vec<Reloc>,
+    strict: bool,
+) -> Result<(), Box<dyn Error>> {
+    let next = (addr + WORDSIZE) as i32;
+    // Data and literals refer to symbols by address, instructions relative to
+    // the next instruction. External symbols are left for the linker.
+    let external = |symbol: &str| Some(Target::Symbol(externs.get_key_value(symbol)?.0.clone()));
+    let absolute = |symbol: &str| match symbols.get(symbol) {
+        Some(&value) => Some((Some(Target::Section(0)), value as i32)),
+        None => Some((Some(external(symbol)?), 0)),
+    };
+    let relative = |symbol: &str| match symbols.get(symbol) {
+        Some(&value) => Some((None, value as i32 - next)),
+        None => Some((Some(external(symbol)?), -next)),
+    };
+    let mut reloc = |offset, kind, target, addend| {
+        relocs.push(Reloc {
+            section: 0,
+            offset,
+            kind,
+            target,
+            addend,
+        })
+    };
+    let (head, ops) = match &tokens[..] {
+        [dot, directive, ..] if dot == "." => match directive.as_str() {
+            "word" => {
+                let mut offset = addr;
+                let ops = operands(&tokens[2..], |op| {
+                    let (value, target) = relocatable(op, absolute)?;
+                    if let Some(target) = target {
+                        reloc(offset, Kind::Abs, target, value);
+                    }
+                    offset += WORDSIZE;
+                    expr::imm(value)
+                })?;
+                (2, ops)
+            }
+            "byte" => {
+                let ops = operands(&tokens[2..], |op| match relocatable(op, absolute)? {
+                    (value, None) => expr::imm(value),
+                    _ => Err(ExprError::NotRelocatable),
+                })?;
+                (2, ops)
+            }
+            "pool" => {
+                pool.fill(tokens);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        },
+        _ if pool::literal(tokens) => {
+            // Load literals from their pool entry
+            let literal = relocatable(&tokens[4..], absolute);
+            let value = literal.as_ref().ok().map(|(value, _)| *value);
+            let (entry, offset) = pool.load(value, addr)?;
+            let (value, target) = literal?;
+            if let Some(target) = target {
+                reloc(entry, Kind::Abs, target, value);
+            }
+            tokens.truncate(3);
+            tokens.push(offset);
+            return Ok(());
+        }
+        _ => {
+            let mut placed = false;
+            let mut far = false;
+            let mut wide = None;
+            let ops = operands(&tokens[1..], |op| match relocatable(op, relative)? {
+                (value, None) => {
+                    // Offsets to symbols must be in range of the instruction
+                    far |= pc_relative(&tokens[0])
+                        && !(-0x80..0x80).contains(&value)
+                        && op.iter().any(|token| symbols.contains_key(token));
+                    // As must immediates, unless synthesized
+                    if fits(&tokens[0], value) == Some(false) {
+                        wide = Some(op.to_vec());
+                    }
+                    expr::imm(value)
+                }
+                // Only a single operand of an instruction may be relocated,
+                // left as zero until linked
+                (value, Some(target)) if !placed => {
+                    placed = true;
+                    reloc(addr, Kind::Rel, target, value + next);
+                    expr::imm(0)
+                }
+                _ => Err(ExprError::NotRelocatable),
+            })?;
+            if far {
+                return Err(ScopeError::OutOfRange.into());
+            }
+            if let Some(imm) = wide {
+                // Only immediates independent of their placement may be
+                // synthesized elsewhere
+                return Err(match strict || relocatable(&imm, absolute)?.1.is_some() {
+                    true => ScopeError::Wide.into(),
+                    false => ScopeError::OutOfRange.into(),
+                });
+            }
+            (1, ops)
+        }
+    };
+    tokens.truncate(head);
+    tokens.extend(ops);
+    Ok(())
+}
+
+/// Checks if an instruction refers to its operand relative to the PC.
+fn pc_relative(mnemonic: &str) -> bool {
+    let mnemonic = mnemonic.to_lowercase();
+    ["goto", "call", "ldr", "str"]
+        .iter()
+        .any(|prefix| mnemonic.starts_with(prefix))
+}
+
+/// Evaluates each non-register operand to an immediate.
+fn operands<F>(tokens: &[String], mut eval: F) -> Result<Vec<String>, ExprError>
+where
+    F: FnMut(&[String]) -> Result<String, ExprError>,
+{
+    if tokens.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut out = Vec::new();
+    for (i, op) in tokens.split(|token| token == ",").enumerate() {
+        if i > 0 {
+            out.push(",".to_string());
+        }
+        match op {
+            [reg] if lex::parse_reg(&reg.to_lowercase()).is_ok() => out.push(reg.clone()),
+            // Registers holding an address
+            [amp, reg] if amp == "&" && lex::parse_reg(&reg.to_lowercase()).is_ok() => {
+                out.extend([amp.clone(), reg.clone()])
+            }
+            _ => out.push(eval(op)?),
+        }
+    }
+    Ok(out)
+}
+
+/// Evaluates an expression whose symbols are offsets from relocatable bases,
+/// returning its value with each base at zero, along with the single base (if
+/// any) it remains relative to.
+fn relocatable<F>(tokens: &[String], lookup: F) -> Result<(i32, Option<Target>), ExprError>
+where
+    F: Fn(&str) -> Option<(Option<Target>, i32)>,
+{
+    // Find each base the expression refers to
+    let mut bases = Vec::new();
+    let symbols = tokens
+        .iter()
+        .filter(|token| token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'));
+    for symbol in symbols {
+        if let Some((Some(base), _)) = lookup(symbol) {
+            if !bases.contains(&base) {
+                bases.push(base);
+            }
+        }
+    }
+    // Evaluate with a single base displaced
+    let displaced = |shift: Option<(&Target, i32)>| {
+        expr::eval(tokens, |symbol| {
+            let (base, value) = lookup(symbol)?;
+            Some(match (base, shift) {
+                (Some(base), Some((target, by))) if base == *target => value + by,
+                _ => value,
+            })
+        })
+    };
+    let value = displaced(None)?;
+    // The result must move along with exactly one base, if any
+    let mut target = None;
+    for base in bases {
+        let one = displaced(Some((&base, 1)))?.wrapping_sub(value);
+        let two = displaced(Some((&base, 2)))?.wrapping_sub(value);
+        match (one, two) {
+            (0, 0) => (),
+            (1, 2) if target.is_none() => target = Some(base),
+            _ => return Err(ExprError::NotRelocatable),
+        }
+    }
+    Ok((value, target))
+}
+
+#[derive(Debug)]
+pub enum ScopeError {
+    BadCount,
+    MissingBody,
+    BadAlias,
+    BadSymbols,
+    Duplicate(String),
+    Nested,
+    OutOfRange,
+    Wide,
+    Predicated,
+    Unused(String),
+    UnusedExtern(String),
+}
+
+impl ScopeError {
+    fn at(self, line: &Line) -> VerboseError {
+        VerboseError::new(self.into(), line)
+    }
+}
+
+impl Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::BadCount => "Expected repetition count".to_string(),
+                Self::MissingBody => "Expected source to repeat".to_string(),
+                Self::BadAlias => "Expected `.reg NAME, REGISTER`".to_string(),
+                Self::BadSymbols => "Expected a list of symbols".to_string(),
+                Self::Duplicate(symbol) => format!("Multiply defined symbol `{}`", symbol),
+                Self::Nested => "Visibility must be declared at file scope".to_string(),
+                Self::OutOfRange => "Target out of range".to_string(),
+                Self::Wide => "Immediate out of range".to_string(),
+                Self::Predicated => {
+                    "Conditional branch cannot follow an `if` when Huffman encoded".to_string()
+                }
+                Self::Unused(symbol) => format!("Unused label `{}`", symbol),
+                Self::UnusedExtern(symbol) => format!("Unused external symbol `{}`", symbol),
+            }
+        )
+    }
+}
+
+impl Error for ScopeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn lines(src: &str) -> Vec<Line> {
+        test_util::lines("test.s", src)
+    }
+
+    #[test]
+    fn repeat() {
+        let src = "
+            .func
+                mov r0, 0d0
+            .repeat 2
+            .begin
+                add r0, 0d1
+                goto next
+            next:
+            .end
+                goto done
+                hlt
+            done:
+                hlt
+            .end
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "mov r0 , 0x0",
+                "add r0 , 0x1",
+                "goto 0x0",
+                "add r0 , 0x1",
+                "goto 0x0",
+                "goto 0x2",
+                "hlt",
+                "hlt",
+            ]
+        );
+        // Repetitions require a count and a body
+        assert!(Scope::new(lines(".repeat r0\nhlt")).is_err());
+        assert!(Scope::new(lines(".repeat 2\nnext:")).is_err());
+    }
+
+    #[test]
+    fn operands() {
+        let src = "
+            start:
+                mov r1, end - start
+                cmp r1, 'a' - 'b'
+                call start
+                goto &LR
+            end:
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "mov r1 , 0x8",
+                "cmp r1 , 0xffff",
+                "call 0xfffa",
+                "goto & LR"
+            ]
+        );
+        // Symbols must be defined
+        let mut scope = Scope::new(lines("mov r0, missing")).unwrap();
+        assert!(scope.subst(false).is_err());
+        // External symbols are left for the linker
+        let mut scope = Scope::new(lines(".extern missing\nldr r0, missing")).unwrap();
+        let (relocs, _) = scope.subst(false).unwrap();
+        assert_eq!(relocs[0].kind, Kind::Rel);
+        assert_eq!(relocs[0].target, Target::Symbol("missing".to_string()));
+        assert_eq!(relocs[0].addend, 0);
+        // Relocatable symbols may not be combined
+        let mut scope = Scope::new(lines(".extern missing\nldr r0, missing + missing")).unwrap();
+        assert!(scope.subst(false).is_err());
+    }
+
+    #[test]
+    fn relax() {
+        let src = "
+            start:
+                goto far
+                ifeq
+                call far
+                ldr r1, far
+                goto start
+            .space 0x100
+            far:
+                gotolt start
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "ldr pc , 0x12",
+                "ifne",
+                "goto 0x6",
+                "mov lr , pc",
+                "add lr , 0x4",
+                "ldr pc , 0xa",
+                "ldr r1 , 0xa",
+                "ldr r1 , r1",
+                "goto 0xffee",
+                ". pool 0x3 , 0x1 , 0x11a , 0x11a , 0x11a",
+                ". space 0x100",
+                "gotoge 0x2",
+                "ldr pc , 0x0",
+                ". pool 0x1 , 0x0 , 0x0",
+            ]
+        );
+        // Stores have no register to hold their target
+        let mut scope = Scope::new(lines("str r0, far\n.space 0x100\nfar:")).unwrap();
+        assert!(scope.subst(false).is_err());
+    }
+
+    #[test]
+    fn lower() {
+        let src = "
+            start:
+                gotoeq start
+                callne next
+            next:
+                gotolt far
+            .space 0x100
+            far:
+                goto start
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        let mut errs = Vec::new();
+        scope.lower(&mut errs);
+        assert!(errs.is_empty());
+        scope.subst(false).unwrap();
+        assert_eq!(scope.symbols["next"], 0x8);
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "ifeq",
+                "goto 0xfffc",
+                "ifne",
+                "call 0x0",
+                "iflt",
+                "ldr pc , 0x2",
+                ". pool 0x1 , 0x1 , 0x110",
+                ". space 0x100",
+                "ldr pc , 0x0",
+                ". pool 0x1 , 0x0 , 0x0",
+            ]
+        );
+        // Predicated branches cannot be lowered
+        let mut scope = Scope::new(lines("ifne\ngotoeq next\nnext:")).unwrap();
+        scope.lower(&mut errs);
+        assert_eq!(
+            errs[0].err.to_string(),
+            "error: Conditional branch cannot follow an `if` when Huffman encoded"
+        );
+    }
+
+    #[test]
+    fn synth() {
+        let src = "
+            start:
+                add a1, 0x400
+                neg r2, 0x41
+                mov r0, 0x1234
+                cmp a0, 0d1000
+                mov r1, end - start
+            .space 0x100
+            end:
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "push r0",
+                "mov r0 , 0x1",
+                "lsl r0 , 0xa",
+                "add a1 , r0",
+                "pop r0",
+                "ldr r2 , 0xe",
+                "ldr r0 , 0xe",
+                "push r1",
+                "ldr r1 , 0xc",
+                "cmp a0 , r1",
+                "pop r1",
+                "ldr r1 , 0x8",
+                ". pool 0x4 , 0x1 , 0xffbf , 0x1234 , 0x3e8 , 0x122",
+                ". space 0x100",
+            ]
+        );
+        // Moves keep the flags of the preceding comparison
+        let mut scope = Scope::new(lines("cmp r0, r1\nmov r1, 0x400\nifne\nhlt")).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(
+            text,
+            [
+                "cmp r0 , r1",
+                "ldr r1 , 0x4",
+                "ifne",
+                "hlt",
+                ". pool 0x1 , 0x0 , 0x400"
+            ]
+        );
+        // Synthesis may be turned off
+        let mut scope = Scope::new(lines("mov r0, 0x50")).unwrap();
+        assert!(scope.subst(true).is_err());
+        // The stack pointer cannot be spared for a scratch register
+        let mut scope = Scope::new(lines("add sp, 0x100")).unwrap();
+        assert!(scope.subst(false).is_err());
+        // Nor can offsets be synthesized away from their placement
+        let mut scope = Scope::new(lines("start:\n.space 0x100\nmov r0, start")).unwrap();
+        assert!(scope.subst(false).is_err());
+    }
+
+    #[test]
+    fn aliases() {
+        let src = "
+            .func
+            .reg count, g0
+            .reg index, count
+                mov count, A0
+                .begin
+                .reg count, a1
+                    add index, count
+                .end
+                sub count, SP
+            .end
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(text, ["mov g0 , A0", "add g0 , a1", "sub g0 , SP"]);
+        // Aliases end with their scope
+        let mut scope = Scope::new(lines(".func\n.reg x, r1\n.end\nmov x, r0")).unwrap();
+        assert!(scope.subst(false).is_err());
+        // Aliases must name a register, without shadowing one
+        assert!(Scope::new(lines(".reg r1, r2")).is_err());
+        assert!(Scope::new(lines(".reg count, 0x1")).is_err());
+    }
+
+    #[test]
+    fn functions() {
+        let src = "
+            .func
+            double:
+                add a0, a0
+            done:
+                goto &lr
+            .end
+                call double
+        ";
+        let mut scope = Scope::new(lines(src)).unwrap();
+        scope.subst(false).unwrap();
+        let text: Vec<_> = scope
+            .flatten()
+            .into_iter()
+            .map(|line| line.tokens.join(" "))
+            .collect();
+        assert_eq!(text, ["add a0 , a0", "goto & lr", "call 0xfffa"]);
+        // Only the function's name is visible outside of it
+        let mut scope = Scope::new(lines(".func\nf:\ndone:\n.end\ngoto done")).unwrap();
+        assert!(scope.subst(false).is_err());
+        let mut scope = Scope::new(lines(".begin\nf:\n.end\ngoto f")).unwrap();
+        assert!(scope.subst(false).is_err());
+        assert!(Scope::new(lines("f:\n.func\nf:\n.end")).is_err());
+    }
+
+    #[test]
+    fn visibility() {
+        let src = "
+            .global _main, helper
+            .extern puts
+            _main:
+                ldr r0, puts
+            helper:
+            .begin
+            helper:
+                hlt
+            .end
+        ";
+        let scope = Scope::new(lines(src)).unwrap();
+        let mut labels: Vec<_> = scope
+            .labels()
+            .into_iter()
+            .map(|symbol| (symbol.name, symbol.bind))
+            .collect();
+        labels.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            labels,
+            [
+                ("_main".to_string(), Bind::Global),
+                ("helper".to_string(), Bind::Global),
+                ("helper".to_string(), Bind::Local),
+            ]
+        );
+        // Symbols may only be defined once per scope
+        let err = Scope::new(lines("loop:\nhlt\nloop:")).unwrap_err();
+        assert_eq!(err.loc.1, 2);
+        assert_eq!(err.notes[0].1 .1, 0);
+        // Exported symbols must be defined at file scope
+        assert!(Scope::new(lines(".global missing")).is_err());
+        assert!(Scope::new(lines(".func\n.extern puts\n.end")).is_err());
+        assert!(Scope::new(lines(".global 0x1")).is_err());
+    }
+
+    #[test]
+    fn unused() {
+        let src = "
+            .extern puts
+            .func
+            double:
+                add a0, a0
+            done:
+                goto &lr
+            .end
+            .entry
+            _main:
+                call double
+            unused:
+                hlt
+            end:
+        ";
+        // Function bodies, names and trailing labels go unreported
+        let mut scope = Scope::new(lines(src)).unwrap();
+        let (_, warnings) = scope.subst(false).unwrap();
+        let warnings: Vec<_> = warnings.iter().map(|warning| warning.to_string()).collect();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("warning: Unused external symbol `puts`"));
+        assert!(warnings[1].starts_with("warning: Unused label `unused`"));
+        // Repeated lines are reported once
+        let mut scope = Scope::new(lines(".repeat 3\nmov r0, missing")).unwrap();
+        assert_eq!(scope.subst(false).unwrap_err().len(), 1);
+    }
 }
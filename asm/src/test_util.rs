@@ -0,0 +1,25 @@
+use std::path::Path;
+
+use isa::Encoding;
+
+use crate::line::Line;
+use crate::obj::Object;
+use crate::unit::Unit;
+
+/// Reads the source lines of a file `path` holding `src`.
+pub fn lines(path: impl AsRef<Path>, src: &str) -> Vec<Line> {
+    src.lines()
+        .enumerate()
+        .map(|(idx, text)| Line::new(path.as_ref().to_path_buf(), idx, text.to_string()))
+        .filter(|line| !line.tokens.is_empty())
+        .collect()
+}
+
+/// Assembles a file `path` holding `src` into an object.
+pub fn obj(path: &str, src: &str, enc: Encoding) -> Object {
+    Unit::new(lines(path, src))
+        .unwrap()
+        .asm(enc, false)
+        .unwrap()
+        .0
+}
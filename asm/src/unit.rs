@@ -1,69 +1,156 @@
-use std::collections::HashSet;
 use std::error::Error;
 use std::fmt::{self, Display};
-use std::path::PathBuf;
+
+use isa::{Encoding, Instruction};
 
 use crate::line::Line;
+use crate::obj::{Bind, Object, Pool, Section, Symbol};
 use crate::scope::Scope;
-use crate::{inst, uarch, VerboseError};
+use crate::{data, pool};
+use crate::{uarch, VerboseError, WORDSIZE};
 
 #[derive(Clone, Debug, Default)]
 pub struct Unit {
-    path: PathBuf,
     global: Scope,
 }
 
 impl Unit {
-    pub fn new(path: PathBuf, lines: Vec<Line>) -> Self {
-        Self {
-            path,
-            global: Scope::new(lines),
-        }
+    pub fn new(lines: Vec<Line>) -> Result<Self, VerboseError> {
+        Ok(Self {
+            global: Scope::new(lines)?,
+        })
     }
 
-    pub fn concat(mut self, mut other: Unit) -> Result<Self, UnitError> {
-        // Ensure no duplicate symbols
-        let set1: HashSet<String> = self.global.symbols.keys().cloned().collect();
-        let set2: HashSet<String> = other.global.symbols.keys().cloned().collect();
-        let dups: Vec<_> = set1.intersection(&set2).cloned().collect();
-        if !dups.is_empty() {
-            return Err(UnitError::DuplicateSymbols(dups));
+    /// Assembles the unit into an object, returning it along with any
+    /// warnings, or else all diagnostics.
+    pub fn asm(
+        mut self,
+        enc: Encoding,
+        strict: bool,
+    ) -> Result<(Object, Vec<VerboseError>), Vec<VerboseError>> {
+        // Lower conditional branches absent from the encoding
+        if enc == Encoding::Huffman {
+            let mut errs = Vec::new();
+            self.global.lower(&mut errs);
+            if !errs.is_empty() {
+                return Err(errs);
+            }
         }
-        // Increment indices in other unit's symbols
-        other
-            .global
-            .symbols
-            .iter_mut()
-            .for_each(|(_, idx)| *idx += self.global.source.len());
-        // Concatenate translation units
-        self.global.source.extend(other.global.source);
-        self.global.symbols.extend(other.global.symbols);
-        // Return combined unit
-        Ok(self)
-    }
-
-    pub fn asm(mut self) -> Result<Vec<uarch>, VerboseError> {
         // Perform symbol substitutions
-        self.global.subst();
+        let (relocs, mut diags) = self.global.subst(strict)?;
+        // Collect defined symbols, followed by those declared external
+        let mut symbols = self.global.labels();
+        symbols.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+        let mut externs: Vec<_> = self
+            .global
+            .externs
+            .iter()
+            .filter(|(name, _)| !self.global.symbols.contains_key(*name))
+            .map(|(name, line)| Symbol {
+                name: name.clone(),
+                bind: Bind::Global,
+                section: None,
+                value: 0,
+                path: line.path.display().to_string(),
+                line: line.number,
+            })
+            .collect();
+        externs.sort_by(|a, b| a.name.cmp(&b.name));
+        // Ensure at most one entry point is declared
+        let mut entries = self.global.entries();
+        entries.sort_by_key(|(addr, _)| *addr);
+        let entry = match entries[..] {
+            [] => None,
+            [(addr, line)] if !addr.is_multiple_of(WORDSIZE) => {
+                return Err(vec![VerboseError::new(UnitError::Misaligned.into(), line)])
+            }
+            [(addr, line)] => Some(Symbol {
+                // Name the entry after any label sharing its address
+                name: symbols
+                    .iter()
+                    .find(|symbol| symbol.value == addr)
+                    .map(|symbol| symbol.name.clone())
+                    .unwrap_or_default(),
+                bind: Bind::Local,
+                section: Some(0),
+                value: addr,
+                path: line.path.display().to_string(),
+                line: line.number,
+            }),
+            [(_, prev), (_, line), ..] => {
+                return Err(vec![VerboseError::new(
+                    UnitError::MultipleEntries.into(),
+                    line,
+                )
+                .note("previously declared here", prev)])
+            }
+        };
+        symbols.extend(externs);
         // Flatten the global scope
         let lines = self.global.flatten();
-        // Assemble instructions
-        lines
-            .into_iter()
-            .map(|line| {
-                inst::asm(&line.tokens).map_err(|err| VerboseError {
-                    err: From::from(err),
-                    loc: (self.path.clone(), line.number),
-                    line: line.text.clone(),
-                })
-            })
-            .collect()
+        // Assemble instructions and data
+        let mut bytes = Vec::new();
+        let mut pools = Vec::new();
+        let mut errs = Vec::new();
+        for line in lines {
+            // Note where pools hold data amongst the code
+            if let Some(entries) = pool::entries(&line.tokens, bytes.len()) {
+                if !entries.is_empty() {
+                    pools.push(Pool {
+                        section: 0,
+                        offset: entries.start,
+                        size: entries.len(),
+                    });
+                }
+            }
+            match asm(&line.tokens, bytes.len(), enc) {
+                Ok(out) => bytes.extend(out),
+                Err(err) => {
+                    // Keep later addresses consistent with the layout
+                    let size = data::size(&line.tokens, bytes.len());
+                    bytes.resize(bytes.len() + size, 0);
+                    errs.push(VerboseError::new(err, &line));
+                }
+            }
+        }
+        if !errs.is_empty() {
+            errs.append(&mut diags);
+            return Err(errs);
+        }
+        let obj = Object {
+            enc,
+            sections: vec![Section {
+                name: "text".to_string(),
+                data: bytes,
+            }],
+            symbols,
+            relocs,
+            pools,
+            entry,
+        };
+        Ok((obj, diags))
+    }
+}
+
+fn asm(tokens: &[String], addr: usize, enc: Encoding) -> Result<Vec<u8>, Box<dyn Error>> {
+    // Emit data directives as-is
+    match tokens {
+        [dot, pool, ..] if dot == "." && pool == "pool" => return pool::emit(tokens, addr, enc),
+        [dot, ..] if dot == "." => return data::emit(tokens, addr),
+        _ => (),
+    }
+    // Instructions must be word aligned
+    if !addr.is_multiple_of(WORDSIZE) {
+        return Err(UnitError::Misaligned.into());
     }
+    let word: uarch = tokens.join(" ").parse::<Instruction>()?.encode(enc)?;
+    Ok(word.to_le_bytes().to_vec())
 }
 
 #[derive(Debug)]
 pub enum UnitError {
-    DuplicateSymbols(Vec<String>),
+    Misaligned,
+    MultipleEntries,
 }
 
 impl Display for UnitError {
@@ -72,7 +159,8 @@ impl Display for UnitError {
             f,
             "{}",
             match self {
-                Self::DuplicateSymbols(dups) => format!("Duplicate symbols: {:?}", dups),
+                Self::Misaligned => "Instruction is not word aligned; use `.align`",
+                Self::MultipleEntries => "Multiple entry points declared",
             }
         )
     }
@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use super::{uarch, RAMSIZE, WORDSIZE};
+use crate::dev::Device;
+use crate::int::{Controller, LINES};
+use crate::proc::Fault;
+use crate::ram::Ram;
+
+/// Routes memory accesses to RAM or to any devices mapped over it.
+#[derive(Debug, Default)]
+pub struct Bus {
+    pub ram: Ram<RAMSIZE>,
+    maps: Vec<Mapping>,
+}
+
+#[derive(Debug)]
+struct Mapping {
+    range: Range<usize>,
+    irq: Option<usize>,
+    dev: Box<dyn Device>,
+}
+
+impl Bus {
+    pub fn map(
+        &mut self,
+        base: uarch,
+        dev: Box<dyn Device>,
+        irq: Option<usize>,
+    ) -> Result<(), BusError> {
+        let range = base as usize..base as usize + dev.size() as usize;
+        // Error checking
+        if !range.start.is_multiple_of(WORDSIZE) || !range.end.is_multiple_of(WORDSIZE) {
+            return Err(BusError::Misaligned);
+        }
+        if range.end > 1 << uarch::BITS {
+            return Err(BusError::OutOfRange);
+        }
+        if irq.is_some_and(|line| line >= LINES) {
+            return Err(BusError::BadIrq);
+        }
+        if self
+            .maps
+            .iter()
+            .any(|map| map.range.start < range.end && range.start < map.range.end)
+        {
+            return Err(BusError::Overlap);
+        }
+        // Map the device
+        self.maps.push(Mapping { range, irq, dev });
+        Ok(())
+    }
+
+    pub fn load(&mut self, addr: uarch) -> Result<uarch, Fault> {
+        match self.find(addr)? {
+            Some((map, offset)) => Ok(map.dev.read(offset)),
+            None => self.ram.load(addr),
+        }
+    }
+
+    pub fn store(&mut self, addr: uarch, word: uarch) -> Result<(), Fault> {
+        match self.find(addr)? {
+            Some((map, offset)) => {
+                map.dev.write(offset, word);
+                Ok(())
+            }
+            None => self.ram.store(addr, word),
+        }
+    }
+
+    /// Advances all devices by one cycle, raising any requested interrupts.
+    pub fn tick(&mut self, intc: &mut Controller) {
+        for map in &mut self.maps {
+            if map.dev.tick() {
+                if let Some(line) = map.irq {
+                    intc.raise(line);
+                }
+            }
+        }
+    }
+
+    fn find(&mut self, addr: uarch) -> Result<Option<(&mut Mapping, uarch)>, Fault> {
+        if !(addr as usize).is_multiple_of(WORDSIZE) {
+            return Err(Fault::Misaligned(addr));
+        }
+        Ok(self
+            .maps
+            .iter_mut()
+            .find(|map| map.range.contains(&(addr as usize)))
+            .map(|map| {
+                let offset = addr - map.range.start as uarch;
+                (map, offset)
+            }))
+    }
+}
+
+impl Display for Bus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.ram)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum BusError {
+    Misaligned,
+    OutOfRange,
+    Overlap,
+    BadIrq,
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Misaligned => "Device range is not word aligned",
+                Self::OutOfRange => "Device range exceeds address space",
+                Self::Overlap => "Device range overlaps another device",
+                Self::BadIrq => "Invalid interrupt request line",
+            }
+        )
+    }
+}
+
+impl Error for BusError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dev::Memory;
+
+    #[test]
+    fn map() {
+        let mut bus = Bus::default();
+        bus.map(0x8000, Box::new(Memory::new(0x0100)), None)
+            .unwrap();
+        // Accesses within the device are routed to it
+        bus.store(0x8010, 0xbeef).unwrap();
+        assert_eq!(bus.load(0x8010), Ok(0xbeef));
+        // Devices may not overlap
+        let mem = Box::new(Memory::new(0x0100));
+        assert!(matches!(bus.map(0x80f0, mem, None), Err(BusError::Overlap)));
+        // Unmapped accesses fall through to RAM
+        bus.store(0x0010, 0xcafe).unwrap();
+        assert_eq!(bus.load(0x0010), Ok(0xcafe));
+        assert_eq!(bus.load(0x8100), Err(Fault::OutOfRange(0x8100)));
+        assert_eq!(bus.load(0x8011), Err(Fault::Misaligned(0x8011)));
+    }
+}
@@ -0,0 +1,29 @@
+use std::fmt::Debug;
+
+use super::uarch;
+
+mod mem;
+mod timer;
+mod uart;
+
+pub use self::mem::Memory;
+pub use self::timer::Timer;
+pub use self::uart::{Uart, UART};
+
+/// Peripheral mapped into the address space by the bus.
+pub trait Device: Debug {
+    /// Size of the device's address range, in bytes.
+    fn size(&self) -> uarch;
+
+    /// Reads the word at `offset` bytes into the device.
+    fn read(&mut self, offset: uarch) -> uarch;
+
+    /// Writes the word at `offset` bytes into the device.
+    fn write(&mut self, offset: uarch, word: uarch);
+
+    /// Advances the device by one cycle, returning whether it requests an
+    /// interrupt.
+    fn tick(&mut self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,26 @@
+use super::Device;
+use crate::{uarch, WORDSIZE};
+
+/// Additional general purpose memory.
+#[derive(Debug)]
+pub struct Memory(Vec<uarch>);
+
+impl Memory {
+    pub fn new(size: uarch) -> Self {
+        Self(vec![0; size as usize / WORDSIZE])
+    }
+}
+
+impl Device for Memory {
+    fn size(&self) -> uarch {
+        (WORDSIZE * self.0.len()) as uarch
+    }
+
+    fn read(&mut self, offset: uarch) -> uarch {
+        self.0[offset as usize / WORDSIZE]
+    }
+
+    fn write(&mut self, offset: uarch, word: uarch) {
+        self.0[offset as usize / WORDSIZE] = word;
+    }
+}
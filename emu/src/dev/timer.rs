@@ -0,0 +1,100 @@
+use super::Device;
+use crate::uarch;
+
+/// Countdown timer, decremented once per cycle.
+///
+/// | Offset   | Register | Operation                                   |
+/// | -------- | -------- | ------------------------------------------- |
+/// | `0x0000` | `RELOAD` | Value to restart the count from on expiry   |
+/// | `0x0002` | `COUNT`  | Cycles remaining until expiry               |
+/// | `0x0004` | `CTRL`   | Control and status flags (see below)        |
+///
+/// | Bit | Flag      | Operation                                      |
+/// | --- | --------- | ---------------------------------------------- |
+/// | `0` | `ENABLE`  | Count down each cycle                          |
+/// | `1` | `IRQ`     | Request an interrupt on expiry                 |
+/// | `2` | `EXPIRED` | Set on expiry; cleared by writing a `1`        |
+///
+/// A timer with a `RELOAD` of zero disables itself on expiry.
+#[derive(Debug, Default)]
+pub struct Timer {
+    reload: uarch,
+    count: uarch,
+    ctrl: uarch,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            ..Default::default()
+        }
+    }
+}
+
+impl Device for Timer {
+    fn size(&self) -> uarch {
+        0x0006
+    }
+
+    fn read(&mut self, offset: uarch) -> uarch {
+        match offset {
+            0x0000 => self.reload,
+            0x0002 => self.count,
+            _ => self.ctrl,
+        }
+    }
+
+    fn write(&mut self, offset: uarch, word: uarch) {
+        match offset {
+            0x0000 => self.reload = word,
+            0x0002 => self.count = word,
+            _ => self.ctrl = (word & 0x0003) | (self.ctrl & !word & 0x0004),
+        }
+    }
+
+    fn tick(&mut self) -> bool {
+        // Only count while enabled
+        if (self.ctrl & 0x0001) == 0 {
+            return false;
+        }
+        self.count = self.count.saturating_sub(1);
+        if self.count != 0 {
+            return false;
+        }
+        // Restart the count, or stop if one-shot
+        self.count = self.reload;
+        if self.reload == 0 {
+            self.ctrl &= !0x0001;
+        }
+        self.ctrl |= 0x0004;
+        (self.ctrl & 0x0002) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn countdown() {
+        let mut timer = Timer::new();
+        // Periodic, with interrupts
+        timer.write(0x0000, 0x0003);
+        timer.write(0x0002, 0x0002);
+        timer.write(0x0004, 0x0003);
+        assert_eq!(
+            (0..8).map(|_| timer.tick()).collect::<Vec<_>>(),
+            [false, true, false, false, true, false, false, true]
+        );
+        assert_eq!(timer.read(0x0004), 0x0007);
+        // Acknowledge expiry
+        timer.write(0x0004, 0x0007);
+        assert_eq!(timer.read(0x0004), 0x0003);
+        // One-shot, polled
+        timer.write(0x0000, 0x0000);
+        timer.write(0x0002, 0x0002);
+        timer.write(0x0004, 0x0001);
+        assert_eq!((0..4).map(|_| timer.tick()).collect::<Vec<_>>(), [false; 4]);
+        assert_eq!(timer.read(0x0004), 0x0004);
+    }
+}
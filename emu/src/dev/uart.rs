@@ -0,0 +1,91 @@
+use std::fmt::Debug;
+use std::io::{Read, Write};
+
+use log::warn;
+
+use super::Device;
+use crate::uarch;
+
+/// Fixed address of the console UART.
+pub const UART: uarch = 0xff00;
+
+/// Serial console, exposing a host's input and output as registers.
+///
+/// | Offset   | Register | Operation                                        |
+/// | -------- | -------- | ------------------------------------------------ |
+/// | `0x0000` | `DATA`   | Read a byte (`0xffff` on EOF), or write a byte   |
+/// | `0x0002` | `STATUS` | Bit `0` set when ready to transmit, bit `1` set  |
+/// |          |          | until the input reaches EOF                      |
+///
+/// Reads of `DATA` wait for input to become available.
+#[derive(Debug)]
+pub struct Uart<R: Read, W: Write> {
+    input: R,
+    output: W,
+    eof: bool,
+}
+
+impl<R: Read, W: Write> Uart<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            eof: false,
+        }
+    }
+
+    fn rx(&mut self) -> uarch {
+        let mut buf = [0; 1];
+        match self.input.read(&mut buf) {
+            Ok(1) => return buf[0] as uarch,
+            Ok(_) => self.eof = true,
+            Err(err) => warn!("Could not read from input: {}", err),
+        }
+        uarch::MAX
+    }
+
+    fn tx(&mut self, byte: u8) {
+        self.output
+            .write_all(&[byte])
+            .and_then(|_| self.output.flush())
+            .unwrap_or_else(|err| warn!("Could not write to output: {}", err));
+    }
+}
+
+impl<R: Read + Debug, W: Write + Debug> Device for Uart<R, W> {
+    fn size(&self) -> uarch {
+        0x0004
+    }
+
+    fn read(&mut self, offset: uarch) -> uarch {
+        match offset {
+            0x0000 => self.rx(),
+            _ => 0x0001 | (((!self.eof) as uarch) << 1),
+        }
+    }
+
+    fn write(&mut self, offset: uarch, word: uarch) {
+        if offset == 0x0000 {
+            self.tx((word & 0x00ff) as u8);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn console() {
+        let mut uart = Uart::new(&b"hi"[..], Vec::new());
+        // Transmit a byte
+        uart.write(0x0000, 'A' as uarch);
+        assert_eq!(uart.output, b"A");
+        // Receive until EOF
+        assert_eq!(uart.read(0x0002), 0x0003);
+        assert_eq!(uart.read(0x0000), 'h' as uarch);
+        assert_eq!(uart.read(0x0000), 'i' as uarch);
+        assert_eq!(uart.read(0x0000), 0xffff);
+        assert_eq!(uart.read(0x0002), 0x0001);
+    }
+}
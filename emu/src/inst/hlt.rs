@@ -0,0 +1,13 @@
+use isa::inst::hlt::Hlt;
+
+use super::Execute;
+use crate::proc::Halt;
+use crate::{Fault, Processor};
+
+impl Execute for Hlt {
+    fn execute(&self, proc: &mut Processor) -> Result<(), Fault> {
+        // Halt the processor
+        proc.halt = Some(Halt::Hlt);
+        Ok(())
+    }
+}
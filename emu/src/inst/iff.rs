@@ -0,0 +1,36 @@
+use isa::inst::iff::Iff;
+
+use super::Execute;
+use crate::{uarch, Fault, Processor, WORDSIZE};
+
+impl Execute for Iff {
+    fn execute(&self, proc: &mut Processor) -> Result<(), Fault> {
+        // Skip the next instruction
+        if !proc.holds(self.cond) {
+            *proc.regs[15] = proc.regs[15].wrapping_add(WORDSIZE as uarch);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip() {
+        let mut proc = Processor::new();
+        // Z set: EQ executes, NE skips
+        *proc.sr = 0x0001;
+        Iff::try_from(0x0e02).unwrap().execute(&mut proc).unwrap();
+        assert_eq!(*proc.regs[15], 0x0000);
+        Iff::try_from(0x0e03).unwrap().execute(&mut proc).unwrap();
+        assert_eq!(*proc.regs[15], 0x0002);
+        // C set: CS executes, CC skips
+        *proc.sr = 0x0008;
+        Iff::try_from(0x0e09).unwrap().execute(&mut proc).unwrap();
+        assert_eq!(*proc.regs[15], 0x0002);
+        Iff::try_from(0x0e08).unwrap().execute(&mut proc).unwrap();
+        assert_eq!(*proc.regs[15], 0x0004);
+    }
+}
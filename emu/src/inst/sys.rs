@@ -0,0 +1,22 @@
+use isa::inst::sys::{Mode, Sys};
+
+use super::Execute;
+use crate::{Fault, Processor};
+
+impl Execute for Sys {
+    fn execute(&self, proc: &mut Processor) -> Result<(), Fault> {
+        match self.mode {
+            // Request service from the host
+            Mode::Sys => proc.syscall = true,
+            // Return from interrupt
+            Mode::Rti => {
+                *proc.regs[15] = *proc.epc;
+                *proc.sr = *proc.esr;
+            }
+            // Enable, disable interrupts
+            Mode::Sei => *proc.sr |= 0x0010,
+            Mode::Cli => *proc.sr &= !0x0010,
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use super::{uarch, WORDSIZE};
+
+/// Number of external interrupt request lines.
+pub const LINES: usize = 6;
+
+/// Entries of the vector table, which holds handler addresses in low memory.
+///
+/// | Address  | Vector                     |
+/// | -------- | -------------------------- |
+/// | `0x0000` | Reset                      |
+/// | `0x0002` | Fault                      |
+/// | `0x0004` | Interrupt request line `0` |
+/// | ...      | ...                        |
+/// | `0x000e` | Interrupt request line `5` |
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vector {
+    Reset,
+    Fault,
+    Irq(usize),
+}
+
+impl Vector {
+    pub fn addr(self) -> uarch {
+        let idx = match self {
+            Self::Reset => 0,
+            Self::Fault => 1,
+            Self::Irq(line) => 2 + line,
+        };
+        (WORDSIZE * idx) as uarch
+    }
+}
+
+/// Interrupt controller, latching requests until the processor takes them.
+#[derive(Debug, Default)]
+pub struct Controller {
+    pending: uarch,
+}
+
+impl Controller {
+    /// Latches a request on `line`, ignoring lines that do not exist.
+    pub fn raise(&mut self, line: usize) {
+        if line < LINES {
+            self.pending |= 1 << line;
+        }
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending != 0
+    }
+
+    /// Takes the highest priority (lowest numbered) pending request.
+    pub fn take(&mut self) -> Option<usize> {
+        self.pending().then(|| {
+            let line = self.pending.trailing_zeros() as usize;
+            self.pending &= !(1 << line);
+            line
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priority() {
+        let mut intc = Controller::default();
+        intc.raise(4);
+        intc.raise(1);
+        intc.raise(4);
+        assert_eq!(intc.take(), Some(1));
+        assert_eq!(intc.take(), Some(4));
+        assert_eq!(intc.take(), None);
+        intc.raise(LINES);
+        assert!(!intc.pending());
+        assert_eq!(Vector::Irq(1).addr(), 0x0006);
+    }
+}
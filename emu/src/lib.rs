@@ -2,48 +2,88 @@
 //!
 //! `emu` is an emulator for the KAP-16 microprocessor.
 
+use std::fmt::{self, Display};
 use std::fs::File;
 use std::io::{self, Read};
 use std::mem;
 use std::path::Path;
 
+use isa::{iarch, uarch, WORDSIZE};
 use log::{debug, error, info, trace, warn};
 
+mod bus;
+mod dev;
 mod inst;
+mod int;
 mod proc;
 mod ram;
 mod reg;
-mod util;
+mod sys;
 
 use self::proc::Processor;
 
-#[allow(non_camel_case_types)]
-type iarch = i16;
-#[allow(non_camel_case_types)]
-type uarch = u16;
+pub use self::bus::BusError;
+pub use self::dev::{Device, Memory, Timer, Uart, UART};
+pub use self::int::{Vector, LINES};
+pub use self::proc::{Fault, Halt};
+pub use self::reg::{Bank, Register};
+pub use self::sys::{Semihost, SysHandler};
+pub use isa::Encoding;
 
-const BANKSIZE: usize = 0x10;
+pub const BANKSIZE: usize = 0x10;
 const RAMSIZE: usize = 0x4000;
-const WORDSIZE: usize = mem::size_of::<uarch>();
 
 #[derive(Default)]
 pub struct Emulator {
     proc: Processor,
+    sys: Option<Box<dyn SysHandler>>,
+    abi: bool,
 }
 
 impl Emulator {
     pub fn new() -> Self {
         Self {
             proc: Processor::new(),
+            ..Default::default()
         }
     }
 
+    pub fn encoding(&mut self, enc: Encoding) {
+        self.proc.enc = enc;
+    }
+
+    /// Logs registers using their ABI names (e.g. `g0` rather than `r4`).
+    pub fn abi(&mut self, abi: bool) {
+        self.abi = abi;
+    }
+
+    pub fn handler(&mut self, sys: Box<dyn SysHandler>) {
+        self.sys = Some(sys);
+    }
+
+    pub fn interrupt(&mut self, line: usize) -> Result<(), BusError> {
+        if line >= LINES {
+            return Err(BusError::BadIrq);
+        }
+        self.proc.intc.raise(line);
+        Ok(())
+    }
+
+    pub fn map(
+        &mut self,
+        base: uarch,
+        dev: Box<dyn Device>,
+        irq: Option<usize>,
+    ) -> Result<(), BusError> {
+        self.proc.bus.map(base, dev, irq)
+    }
+
     pub fn load(&mut self, file: &Path) -> io::Result<()> {
         // Open the ROM file
         let mut f = File::open(file)?;
 
         // Read its contents into memory
-        let buf = &mut self.proc.ram.0;
+        let buf = &mut self.proc.bus.ram.0;
         let read = f.read(buf)?;
 
         // Error checking
@@ -63,18 +103,260 @@ impl Emulator {
             );
         }
 
+        // Start from the image's entry point, unless the ROM predates the
+        // vector table
+        if self.proc.enc == Encoding::Huffman {
+            self.proc.reset();
+        }
+
         Ok(())
     }
 
-    pub fn main(&mut self) {
+    pub fn main(&mut self) -> Exit {
+        let mut cycles = 0;
         loop {
-            let instr = self.proc.cycle();
-            info!("{}", instr);
-            debug!("{}", self.proc);
-            trace!("{}", self.proc.ram);
+            self.proc.poll();
+            let pc = *self.proc.regs[15];
+            let res = self.proc.cycle();
+            // Advance any devices
+            self.proc.bus.tick(&mut self.proc.intc);
+            let instr = match res {
+                Ok(instr) => instr,
+                Err(fault) => {
+                    // Report the faulting instruction
+                    let msg = match self.proc.bus.ram.load(pc) {
+                        Ok(word) => format!("{:#06x}: {:04x}: {}", pc, word, fault),
+                        Err(_) => format!("{:#06x}: {}", pc, fault),
+                    };
+                    // Route the fault to its handler if possible
+                    if self.proc.except(pc) {
+                        warn!("{}", msg);
+                        cycles += 1;
+                        continue;
+                    }
+                    error!("{}", msg);
+                    return Exit {
+                        reason: Halt::Fault(fault),
+                        cycles,
+                        pc,
+                    };
+                }
+            };
+            cycles += 1;
+            match self.abi {
+                true => {
+                    info!("{:#}", instr);
+                    debug!("{:#}", self.proc);
+                }
+                false => {
+                    info!("{}", instr);
+                    debug!("{}", self.proc);
+                }
+            }
+            trace!("{}", self.proc.bus);
+            // Service any pending system call
+            if mem::take(&mut self.proc.syscall) {
+                match self.sys.as_mut() {
+                    Some(sys) => self.proc.halt = sys.sys(&mut self.proc.regs),
+                    None => warn!("Ignored system call; no handler installed."),
+                }
+            }
+            // Stop once the processor has halted
+            if let Some(reason) = self.proc.halt {
+                return Exit {
+                    reason,
+                    cycles,
+                    pc: *self.proc.regs[15],
+                };
+            }
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exit {
+    pub reason: Halt,
+    pub cycles: usize,
+    pub pc: uarch,
+}
+
+impl Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} after {} cycles (PC: {:#06x})",
+            self.reason, self.cycles, self.pc
+        )
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halt() {
+        let mut e = Emulator::new();
+        // mov r0, 0d1; add r0, r0; hlt
+        let rom: [uarch; 3] = [0x7081, 0xc000, 0x0c00];
+        for (i, word) in rom.into_iter().enumerate() {
+            e.proc.bus.ram[(WORDSIZE * i) as uarch] = word;
+        }
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Hlt);
+        assert_eq!(exit.cycles, 3);
+        assert_eq!(exit.pc, 0x0006);
+        assert_eq!(*e.proc.regs[0], 0x0002);
+    }
+
+    /// Loads an image from `(addr, word)` pairs, as if read from a ROM.
+    fn boot(name: &str, words: &[(uarch, uarch)]) -> Emulator {
+        let path = std::env::temp_dir().join(format!("emu-{}-{}", name, std::process::id()));
+        let mut bytes = vec![0; RAMSIZE];
+        for &(addr, word) in words {
+            let addr = addr as usize;
+            bytes[addr..addr + WORDSIZE].copy_from_slice(&word.to_le_bytes());
+        }
+        std::fs::write(&path, bytes).unwrap();
+        let mut e = Emulator::new();
+        e.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        e
+    }
+
+    #[test]
+    fn reset() {
+        let mut e = boot(
+            "reset",
+            &[
+                (0x0000, 0x0010), // reset vector
+                (0x0010, 0x7081), // mov r0, 0d1
+                (0x0012, 0x0c00), // hlt
+            ],
+        );
+        // Execution begins at the entry point
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Hlt);
+        assert_eq!(exit.cycles, 2);
+        assert_eq!(exit.pc, 0x0014);
+        assert_eq!(*e.proc.regs[0], 0x0001);
+    }
+
+    #[test]
+    fn legacy() {
+        // Legacy ROMs run from the start of memory
+        for rom in ["empty.rom", "full.rom"] {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../doc/roms")
+                .join(rom);
+            let mut e = Emulator::new();
+            e.encoding(Encoding::Legacy);
+            e.load(&path).unwrap();
+            assert_eq!(*e.proc.regs[15], 0x0000);
+            let exit = e.main();
+            assert_eq!(exit.reason, Halt::Fault(Fault::OutOfRange(0x4000)));
+            assert_eq!(exit.cycles, 0x2000);
+        }
+    }
+
+    #[test]
+    fn interrupt() {
+        let mut e = boot(
+            "interrupt",
+            &[
+                (0x0000, 0x0010), // reset vector
+                (0x0002, 0x0020), // fault vector
+                (0x0004, 0x0030), // irq0 vector
+                (0x0010, 0x0802), // sei
+                (0x0012, 0x7081), // mov r0, 0d1
+                (0x0014, 0xffff), // (illegal)
+                (0x0020, 0x7283), // mov r2, 0d3
+                (0x0022, 0x0c00), // hlt
+                (0x0030, 0x7182), // mov r1, 0d2
+                (0x0032, 0x0801), // rti
+            ],
+        );
+        e.interrupt(0).unwrap();
+        assert!(e.interrupt(LINES).is_err());
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Hlt);
+        assert_eq!(exit.cycles, 7);
+        assert_eq!(exit.pc, 0x0024);
+        assert_eq!(*e.proc.regs[0], 0x0001);
+        assert_eq!(*e.proc.regs[1], 0x0002);
+        assert_eq!(*e.proc.regs[2], 0x0003);
+        // Faults are saved with their own address
+        assert_eq!(*e.proc.epc, 0x0014);
+        assert_eq!(*e.proc.esr & 0x0010, 0x0010);
+    }
+
+    #[test]
+    fn timer() {
+        let mut e = boot(
+            "timer",
+            &[
+                (0x0000, 0x0010), // reset vector
+                (0x0004, 0x0030), // irq0 vector
+                (0x0010, 0x0802), // sei
+                (0x0012, 0xc081), // add r0, 0d1
+                (0x0014, 0x00fe), // goto 0xfffc
+                (0x0030, 0x0c00), // hlt
+            ],
+        );
+        // Expire after five cycles
+        let mut timer = Timer::new();
+        timer.write(0x0002, 0x0005);
+        timer.write(0x0004, 0x0003);
+        e.map(0x8000, Box::new(timer), Some(0)).unwrap();
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Hlt);
+        assert_eq!(exit.cycles, 6);
+        assert_eq!(*e.proc.regs[0], 0x0002);
+        assert_eq!(*e.proc.epc, 0x0012);
+    }
+
+    #[test]
+    fn fault() {
+        let mut e = Emulator::new();
+        // mov r0, 0d1; ldr r1, r0
+        let rom: [uarch; 2] = [0x7081, 0x3100];
+        for (i, word) in rom.into_iter().enumerate() {
+            e.proc.bus.ram[(WORDSIZE * i) as uarch] = word;
+        }
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Fault(Fault::Misaligned(0x0001)));
+        assert_eq!(exit.cycles, 1);
+        assert_eq!(exit.pc, 0x0002);
+        // shf with unused mode
+        let mut e = Emulator::new();
+        e.proc.bus.ram[0] = 0xf030;
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Fault(Fault::Illegal(0xf030)));
+        assert_eq!(exit.pc, 0x0000);
+    }
+
+    #[test]
+    fn wrap() {
+        // push r0, with an empty stack
+        let mut e = Emulator::new();
+        e.proc.bus.ram[0x0000] = 0x2040;
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Fault(Fault::OutOfRange(0xfffe)));
+        assert_eq!(*e.proc.regs[13], 0xfffe);
+        // pop r0, from the top of the address space
+        let mut e = Emulator::new();
+        e.proc.bus.ram[0x0000] = 0x3040;
+        *e.proc.regs[13] = 0xfffe;
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Fault(Fault::OutOfRange(0xfffe)));
+        assert_eq!(*e.proc.regs[13], 0x0000);
+        // goto &r0, to the last word of the address space
+        let mut e = Emulator::new();
+        e.proc.bus.ram[0x0000] = 0x0000;
+        *e.proc.regs[0] = 0xfffe;
+        let exit = e.main();
+        assert_eq!(exit.reason, Halt::Fault(Fault::OutOfRange(0xfffe)));
+        assert_eq!(exit.cycles, 1);
+        assert_eq!(*e.proc.regs[15], 0x0000);
+    }
+}
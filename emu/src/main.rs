@@ -1,10 +1,12 @@
+use std::io;
 use std::path::PathBuf;
 use std::process;
+use std::str::FromStr;
 
 use clap::{Parser, ValueHint};
-use emu::Emulator;
+use emu::{Device, Emulator, Encoding, Halt, Memory, Semihost, Timer, Uart, UART};
 use env_logger as logger;
-use log::error;
+use log::{error, info};
 
 fn main() {
     // Initialize logger
@@ -19,13 +21,51 @@ fn main() {
 
     // Instantiate an emulator
     let mut e = Emulator::new();
+    // Select the instruction encoding
+    if args.legacy {
+        e.encoding(Encoding::Legacy);
+    }
+    // Log registers by their ABI names
+    e.abi(args.abi);
+    // Attach the console to the host
+    e.map(UART, Box::new(Uart::new(io::stdin(), io::stdout())), None)
+        .unwrap();
+    // Map any devices onto the bus
+    for spec in args.device {
+        let dev: Box<dyn Device> = match &*spec.name {
+            "mem" => Box::new(Memory::new(0x1000)),
+            "timer" => Box::new(Timer::new()),
+            name => {
+                error!("`{}`: Unknown device", name);
+                process::exit(1)
+            }
+        };
+        e.map(spec.base, dev, spec.irq).unwrap_or_else(|err| {
+            error!("`{}`: {}", spec.name, err);
+            process::exit(1)
+        });
+    }
     // Load the ROM into memory
     e.load(&args.rom).unwrap_or_else(|err| {
         error!("`{}`: {}", &args.rom.display(), err);
         process::exit(1)
     });
+    // Service system calls from the host
+    e.handler(Box::new(Semihost::new(io::stdin(), io::stdout())));
     // Run the emulator
-    e.main();
+    let exit = e.main();
+    // Forward the program's exit code
+    match exit.reason {
+        Halt::Hlt => info!("{}", exit),
+        Halt::Exit(code) => {
+            info!("{}", exit);
+            process::exit(code as i32)
+        }
+        Halt::Fault(_) => {
+            error!("{}", exit);
+            process::exit(1)
+        }
+    }
 }
 
 /// Emulator for the KAP-16 processor.
@@ -37,8 +77,56 @@ struct Args {
     #[clap(value_hint = ValueHint::FilePath)]
     rom: PathBuf,
 
+    /// Map a device onto the bus (e.g. `mem@0x8000`, `timer@0xff10:0`)
+    #[clap(short, long)]
+    #[clap(value_name = "NAME@ADDR[:IRQ]")]
+    device: Vec<DeviceSpec>,
+
+    /// Decode using the legacy fixed-width opcodes
+    #[clap(long)]
+    legacy: bool,
+
+    /// Log registers using their ABI names (e.g. `g0`)
+    #[clap(long)]
+    abi: bool,
+
     /// Use verbose output (-v, -vv, -vvv, etc.)
     #[clap(short, long)]
     #[clap(parse(from_occurrences))]
     verbose: u8,
 }
+
+#[derive(Debug)]
+struct DeviceSpec {
+    name: String,
+    base: u16,
+    irq: Option<usize>,
+}
+
+impl FromStr for DeviceSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split into name, address and interrupt line
+        let (name, addr) = s.split_once('@').ok_or("expected NAME@ADDR")?;
+        let (addr, irq) = match addr.split_once(':') {
+            Some((addr, irq)) => (addr, Some(irq)),
+            None => (addr, None),
+        };
+        // Parse each part
+        let base = match addr.strip_prefix("0x") {
+            Some(hex) => u16::from_str_radix(hex, 16),
+            None => addr.parse(),
+        }
+        .map_err(|err| format!("bad address `{}`: {}", addr, err))?;
+        let irq = irq
+            .map(|irq| irq.parse())
+            .transpose()
+            .map_err(|err| format!("bad interrupt line: {}", err))?;
+        Ok(Self {
+            name: name.to_string(),
+            base,
+            irq,
+        })
+    }
+}
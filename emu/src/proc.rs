@@ -1,15 +1,25 @@
+use std::error::Error;
 use std::fmt::{self, Display};
 
-use super::{uarch, BANKSIZE, RAMSIZE, WORDSIZE};
-use crate::inst::{self, Instruction};
-use crate::ram::Ram;
+use isa::{lex, Cond, Encoding, Instruction};
+
+use super::{uarch, BANKSIZE, WORDSIZE};
+use crate::bus::Bus;
+use crate::inst::Execute;
+use crate::int::{Controller, Vector};
 use crate::reg::{Bank, Register};
 
 #[derive(Debug, Default)]
 pub struct Processor {
     pub regs: Bank<BANKSIZE>,
     pub sr: Register,
-    pub ram: Ram<RAMSIZE>,
+    pub epc: Register,
+    pub esr: Register,
+    pub bus: Bus,
+    pub intc: Controller,
+    pub enc: Encoding,
+    pub halt: Option<Halt>,
+    pub syscall: bool,
 }
 
 impl Processor {
@@ -19,17 +29,81 @@ impl Processor {
         }
     }
 
-    pub fn cycle(&mut self) -> Box<dyn Instruction> {
+    /// Resets the processor, jumping to the address held in the reset vector.
+    pub fn reset(&mut self) {
+        *self.regs[15] = self.bus.ram[Vector::Reset.addr()];
+    }
+
+    pub fn cycle(&mut self) -> Result<Instruction, Fault> {
         let pc = *self.regs[15];
-        *self.regs[15] += WORDSIZE as uarch;
-        let word = self.ram[pc];
-        let instr = inst::decode(word);
-        instr.execute(self);
-        instr
+        *self.regs[15] = self.regs[15].wrapping_add(WORDSIZE as uarch);
+        let word = self.bus.load(pc)?;
+        let instr = Instruction::decode(word, self.enc).map_err(|_| Fault::Illegal(word))?;
+        instr.execute(self)?;
+        Ok(instr)
+    }
+
+    /// Takes the next pending interrupt, if interrupts are enabled.
+    pub fn poll(&mut self) {
+        if self.ie() {
+            if let Some(line) = self.intc.take() {
+                self.enter(Vector::Irq(line));
+            }
+        }
+    }
+
+    /// Routes a fault at `pc` to its handler, if interrupts are enabled.
+    pub fn except(&mut self, pc: uarch) -> bool {
+        if !self.ie() {
+            return false;
+        }
+        *self.regs[15] = pc;
+        self.enter(Vector::Fault);
+        true
+    }
+
+    fn enter(&mut self, vector: Vector) {
+        // Save the interrupted context
+        *self.epc = *self.regs[15];
+        *self.esr = *self.sr;
+        // Mask further interrupts
+        *self.sr &= !0x0010;
+        // Jump to the handler
+        *self.regs[15] = self.bus.ram[vector.addr()];
+    }
+
+    /// Tests a condition against the condition code flags.
+    pub fn holds(&self, cond: Cond) -> bool {
+        let zero = (*self.sr & 0x0001) != 0;
+        let negative = (*self.sr & 0x0002) != 0;
+        let overflow = (*self.sr & 0x0004) != 0;
+        let carry = (*self.sr & 0x0008) != 0;
+        // Orderings are signed
+        match cond {
+            Cond::Al => true,
+            Cond::Nv => false,
+            Cond::Eq => zero,
+            Cond::Ne => !zero,
+            Cond::Lt => negative ^ overflow,
+            Cond::Le => zero | (negative ^ overflow),
+            Cond::Ge => !(negative ^ overflow),
+            Cond::Gt => !zero & !(negative ^ overflow),
+            Cond::Cc => !carry,
+            Cond::Cs => carry,
+            Cond::Vc => !overflow,
+            Cond::Vs => overflow,
+            Cond::Pl => !negative,
+            Cond::Mi => negative,
+        }
+    }
+
+    fn ie(&self) -> bool {
+        (*self.sr & 0x0010) != 0
     }
 
     fn flags(&self) -> Vec<Flag> {
         let mut flags = Vec::new();
+        (*self.sr & 0x0010 != 0).then(|| flags.push(Flag::Interrupt));
         (*self.sr & 0x0008 != 0).then(|| flags.push(Flag::Carry));
         (*self.sr & 0x0004 != 0).then(|| flags.push(Flag::Overflow));
         (*self.sr & 0x0002 != 0).then(|| flags.push(Flag::Negative));
@@ -41,13 +115,22 @@ impl Processor {
 impl Display for Processor {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         for (i, reg) in self.regs.iter().enumerate() {
-            write!(f, "R{:02}: {}", i, reg)?;
+            match f.alternate() {
+                true => write!(
+                    f,
+                    "{:<3}: {}",
+                    lex::reg_name(i as uarch, true).to_uppercase(),
+                    reg
+                )?,
+                false => write!(f, "R{:02}: {}", i, reg)?,
+            }
             if (i + 1) % 4 != 0 {
                 write!(f, ", ")?;
             } else {
                 writeln!(f)?;
             }
         }
+        writeln!(f, "EPC: {}, ESR: {}", self.epc, self.esr)?;
         write!(f, "SR : {}, {:?}", self.sr, self.flags())?;
         write!(f, "")
     }
@@ -55,8 +138,49 @@ impl Display for Processor {
 
 #[derive(Debug)]
 enum Flag {
+    Interrupt,
     Carry,
     Overflow,
     Negative,
     Zero,
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Halt {
+    Hlt,
+    Exit(uarch),
+    Fault(Fault),
+}
+
+impl Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Hlt => "Executed halt instruction".to_string(),
+                Self::Exit(code) => format!("Exited with code {}", code),
+                Self::Fault(fault) => format!("Faulted with {}", fault),
+            }
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fault {
+    Illegal(uarch),
+    Misaligned(uarch),
+    OutOfRange(uarch),
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Illegal(word) => write!(f, "illegal instruction {:#06x}", word),
+            Self::Misaligned(addr) => write!(f, "misaligned access at {:#06x}", addr),
+            Self::OutOfRange(addr) => write!(f, "out-of-range access at {:#06x}", addr),
+        }
+    }
+}
+
+impl Error for Fault {}
@@ -3,10 +3,30 @@ use std::mem;
 use std::ops::{Deref, DerefMut, Index, IndexMut};
 
 use super::{uarch, WORDSIZE};
+use crate::proc::Fault;
 
 #[derive(Debug)]
 pub struct Ram<const N: usize>(pub [u8; N]);
 
+impl<const N: usize> Ram<N> {
+    pub fn load(&self, addr: uarch) -> Result<uarch, Fault> {
+        Ok(self[Self::check(addr)?])
+    }
+
+    pub fn store(&mut self, addr: uarch, word: uarch) -> Result<(), Fault> {
+        self[Self::check(addr)?] = word;
+        Ok(())
+    }
+
+    fn check(addr: uarch) -> Result<uarch, Fault> {
+        match addr as usize {
+            idx if !idx.is_multiple_of(WORDSIZE) => Err(Fault::Misaligned(addr)),
+            idx if idx >= N => Err(Fault::OutOfRange(addr)),
+            _ => Ok(addr),
+        }
+    }
+}
+
 impl<const N: usize> Display for Ram<N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         const ROWSIZE: usize = mem::size_of::<usize>();
@@ -51,7 +71,7 @@ impl<const N: usize> Index<uarch> for Ram<N> {
 
     fn index(&self, idx: uarch) -> &Self::Output {
         let idx = idx as usize;
-        assert!((idx % WORDSIZE) == 0);
+        assert!(idx.is_multiple_of(WORDSIZE));
         unsafe { &self.0.align_to::<uarch>().1[idx / WORDSIZE] }
     }
 }
@@ -59,7 +79,7 @@ impl<const N: usize> Index<uarch> for Ram<N> {
 impl<const N: usize> IndexMut<uarch> for Ram<N> {
     fn index_mut(&mut self, idx: uarch) -> &mut Self::Output {
         let idx = idx as usize;
-        assert!((idx % WORDSIZE) == 0);
+        assert!(idx.is_multiple_of(WORDSIZE));
         unsafe { &mut self.0.align_to_mut::<uarch>().1[idx / WORDSIZE] }
     }
 }
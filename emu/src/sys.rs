@@ -0,0 +1,95 @@
+use std::io::{Read, Write};
+
+use log::warn;
+
+use crate::proc::Halt;
+use crate::reg::Bank;
+use crate::{iarch, uarch, BANKSIZE};
+
+/// Host interface for servicing `SYS` instructions.
+pub trait SysHandler {
+    /// Services a `SYS` using the processor's registers, optionally halting it.
+    fn sys(&mut self, regs: &mut Bank<BANKSIZE>) -> Option<Halt>;
+}
+
+/// Semihosting services, selected by the value of `A3`.
+///
+/// | Service | Operation                                |
+/// | ------- | ---------------------------------------- |
+/// | `0x0`   | Exit with the code in `A0`               |
+/// | `0x1`   | Print the character in `A0`              |
+/// | `0x2`   | Print the signed integer in `A0`         |
+/// | `0x3`   | Read a byte into `A0` (`0xffff` on EOF)  |
+#[derive(Debug)]
+pub struct Semihost<R: Read, W: Write> {
+    input: R,
+    output: W,
+}
+
+impl<R: Read, W: Write> Semihost<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    fn getc(&mut self) -> uarch {
+        let mut buf = [0; 1];
+        match self.input.read(&mut buf) {
+            Ok(1) => buf[0] as uarch,
+            Ok(_) => uarch::MAX,
+            Err(err) => {
+                warn!("Could not read from input: {}", err);
+                uarch::MAX
+            }
+        }
+    }
+
+    fn puts(&mut self, s: &str) {
+        self.output
+            .write_all(s.as_bytes())
+            .and_then(|_| self.output.flush())
+            .unwrap_or_else(|err| warn!("Could not write to output: {}", err));
+    }
+}
+
+impl<R: Read, W: Write> SysHandler for Semihost<R, W> {
+    fn sys(&mut self, regs: &mut Bank<BANKSIZE>) -> Option<Halt> {
+        let arg = *regs[0];
+        match *regs[3] {
+            0x0 => return Some(Halt::Exit(arg)),
+            0x1 => self.puts(&((arg & 0x00ff) as u8 as char).to_string()),
+            0x2 => self.puts(&(arg as iarch).to_string()),
+            0x3 => *regs[0] = self.getc(),
+            service => warn!("Unknown system service: {:#06x}", service),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn services() {
+        let mut host = Semihost::new(&b"k"[..], Vec::new());
+        let mut regs = Bank::default();
+        // Print a character, then an integer
+        *regs[3] = 0x1;
+        *regs[0] = 'A' as uarch;
+        assert_eq!(host.sys(&mut regs), None);
+        *regs[3] = 0x2;
+        *regs[0] = -42i16 as uarch;
+        assert_eq!(host.sys(&mut regs), None);
+        assert_eq!(host.output, b"A-42");
+        // Read a byte until EOF
+        *regs[3] = 0x3;
+        host.sys(&mut regs);
+        assert_eq!(*regs[0], 'k' as uarch);
+        host.sys(&mut regs);
+        assert_eq!(*regs[0], 0xffff);
+        // Exit with a code
+        *regs[3] = 0x0;
+        *regs[0] = 0x7;
+        assert_eq!(host.sys(&mut regs), Some(Halt::Exit(0x7)));
+    }
+}
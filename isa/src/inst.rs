@@ -0,0 +1,339 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use crate::lex::LexemeError;
+use crate::{lex, uarch};
+
+pub mod add;
+pub mod and;
+pub mod bra;
+pub mod cmp;
+pub mod hlt;
+pub mod iff;
+pub mod ldr;
+mod legacy;
+pub mod mov;
+pub mod mul;
+pub mod orr;
+pub mod shf;
+pub mod str;
+pub mod sub;
+pub mod sys;
+pub mod xor;
+
+use self::add::Add;
+use self::and::And;
+use self::bra::Bra;
+use self::cmp::Cmp;
+use self::hlt::Hlt;
+use self::iff::Iff;
+use self::ldr::Ldr;
+use self::mov::Mov;
+use self::mul::Mul;
+use self::orr::Orr;
+use self::shf::Shf;
+use self::str::Str;
+use self::sub::Sub;
+use self::sys::Sys;
+use self::xor::Xor;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Huffman,
+    Legacy,
+}
+
+#[derive(Debug)]
+pub enum Instruction {
+    Add(Add),
+    And(And),
+    Bra(Bra),
+    Cmp(Cmp),
+    Hlt(Hlt),
+    Iff(Iff),
+    Ldr(Ldr),
+    Mov(Mov),
+    Mul(Mul),
+    Orr(Orr),
+    Shf(Shf),
+    Str(Str),
+    Sub(Sub),
+    Sys(Sys),
+    Xor(Xor),
+}
+
+impl Instruction {
+    pub fn decode(word: uarch, enc: Encoding) -> Result<Self, InstructionError> {
+        match enc {
+            Encoding::Huffman => Self::try_from(word),
+            Encoding::Legacy => legacy::decode(word),
+        }
+    }
+
+    pub fn encode(self, enc: Encoding) -> Result<uarch, InstructionError> {
+        match (enc, self) {
+            // Conditions must instead be expressed using IFF
+            (Encoding::Huffman, Self::Bra(instr)) if instr.cond != Cond::Al => {
+                Err(InstructionError::Unencodable)
+            }
+            (Encoding::Huffman, instr) => Ok(instr.into()),
+            (Encoding::Legacy, Self::Bra(instr)) => instr.into_legacy(),
+            (Encoding::Legacy, instr) => Ok(legacy::encode(instr.into())),
+        }
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add(instr) => Display::fmt(instr, f),
+            Self::And(instr) => Display::fmt(instr, f),
+            Self::Bra(instr) => Display::fmt(instr, f),
+            Self::Cmp(instr) => Display::fmt(instr, f),
+            Self::Hlt(instr) => Display::fmt(instr, f),
+            Self::Iff(instr) => Display::fmt(instr, f),
+            Self::Ldr(instr) => Display::fmt(instr, f),
+            Self::Mov(instr) => Display::fmt(instr, f),
+            Self::Mul(instr) => Display::fmt(instr, f),
+            Self::Orr(instr) => Display::fmt(instr, f),
+            Self::Shf(instr) => Display::fmt(instr, f),
+            Self::Str(instr) => Display::fmt(instr, f),
+            Self::Sub(instr) => Display::fmt(instr, f),
+            Self::Sys(instr) => Display::fmt(instr, f),
+            Self::Xor(instr) => Display::fmt(instr, f),
+        }
+    }
+}
+
+impl TryFrom<uarch> for Instruction {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        Ok(match word {
+            0x0000..=0x07ff => Self::Bra(Bra::try_from(word)?), // 00000   => BRA
+            0x0800..=0x0bff => Self::Sys(Sys::try_from(word)?), // 000010  => SYS
+            0x0c00..=0x0dff => Self::Hlt(Hlt::try_from(word)?), // 0000110 => HLT
+            0x0e00..=0x0fff => Self::Iff(Iff::try_from(word)?), // 0000111 => IFF
+            0x1000..=0x1fff => Self::Xor(Xor::try_from(word)?), // 0001    => XOR
+            0x2000..=0x2fff => Self::Str(Str::try_from(word)?), // 0010    => STR
+            0x3000..=0x3fff => Self::Ldr(Ldr::try_from(word)?), // 0011    => LDR
+            0x4000..=0x5fff => Self::Sub(Sub::try_from(word)?), // 010     => SUB
+            0x6000..=0x6fff => Self::Mul(Mul::try_from(word)?), // 0110    => MUL
+            0x7000..=0x7fff => Self::Mov(Mov::try_from(word)?), // 0111    => MOV
+            0x8000..=0xbfff => Self::Cmp(Cmp::try_from(word)?), // 10      => CMP
+            0xc000..=0xcfff => Self::Add(Add::try_from(word)?), // 1100    => ADD
+            0xd000..=0xdfff => Self::Orr(Orr::try_from(word)?), // 1101    => ORR
+            0xe000..=0xefff => Self::And(And::try_from(word)?), // 1110    => AND
+            0xf000..=0xffff => Self::Shf(Shf::try_from(word)?), // 1111    => SHF
+        })
+    }
+}
+
+impl From<Instruction> for uarch {
+    fn from(instr: Instruction) -> Self {
+        match instr {
+            Instruction::Add(instr) => instr.into(),
+            Instruction::And(instr) => instr.into(),
+            Instruction::Bra(instr) => instr.into(),
+            Instruction::Cmp(instr) => instr.into(),
+            Instruction::Hlt(instr) => instr.into(),
+            Instruction::Iff(instr) => instr.into(),
+            Instruction::Ldr(instr) => instr.into(),
+            Instruction::Mov(instr) => instr.into(),
+            Instruction::Mul(instr) => instr.into(),
+            Instruction::Orr(instr) => instr.into(),
+            Instruction::Shf(instr) => instr.into(),
+            Instruction::Str(instr) => instr.into(),
+            Instruction::Sub(instr) => instr.into(),
+            Instruction::Sys(instr) => instr.into(),
+            Instruction::Xor(instr) => instr.into(),
+        }
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only operate on lowercase strings
+        // (also creates an owned String from &str)
+        let s = s.to_lowercase();
+        // Split into constituent tokens
+        let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
+        // Parse using the instruction's mnemonic
+        Ok(match &*tokens[0] {
+            "add" => Self::Add(s.parse()?),
+            "and" => Self::And(s.parse()?),
+            "goto" | "gotoal" | "gotoeq" | "gotone" | "gotolt" | "gotole" | "gotoge" | "gotogt"
+            | "call" | "callal" | "calleq" | "callne" | "calllt" | "callle" | "callge"
+            | "callgt" => Self::Bra(s.parse()?),
+            "cmp" | "cmn" | "tst" | "teq" => Self::Cmp(s.parse()?),
+            "hlt" => Self::Hlt(s.parse()?),
+            "if" | "ifal" | "ifnv" | "ifeq" | "ifne" | "iflt" | "ifle" | "ifge" | "ifgt"
+            | "ifcc" | "ifcs" | "ifvc" | "ifvs" | "ifpl" | "ifmi" => Self::Iff(s.parse()?),
+            "ldr" | "pop" => Self::Ldr(s.parse()?),
+            "mov" | "neg" | "not" => Self::Mov(s.parse()?),
+            "mul" => Self::Mul(s.parse()?),
+            "orr" => Self::Orr(s.parse()?),
+            "lsr" | "asr" | "ror" | "lsl" | "asl" | "rol" => Self::Shf(s.parse()?),
+            "str" | "push" => Self::Str(s.parse()?),
+            "sub" | "rsb" => Self::Sub(s.parse()?),
+            "sys" | "rti" | "sei" | "cli" => Self::Sys(s.parse()?),
+            "xor" => Self::Xor(s.parse()?),
+            _ => return Err(InstructionError::UnknownInstruction.into()),
+        })
+    }
+}
+
+/// Condition on the condition code flags, as tested by IFF and by legacy
+/// conditional branches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cond {
+    Al = 0b0000,
+    Nv = 0b0001,
+    Eq = 0b0010,
+    Ne = 0b0011,
+    Lt = 0b0100,
+    Le = 0b0101,
+    Ge = 0b0110,
+    Gt = 0b0111,
+    Cc = 0b1000,
+    Cs = 0b1001,
+    Vc = 0b1010,
+    Vs = 0b1011,
+    Pl = 0b1100,
+    Mi = 0b1101,
+}
+
+impl Cond {
+    /// Condition holding exactly when this one does not.
+    pub fn invert(self) -> Self {
+        match self {
+            Self::Al => Self::Nv,
+            Self::Nv => Self::Al,
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::Lt => Self::Ge,
+            Self::Ge => Self::Lt,
+            Self::Le => Self::Gt,
+            Self::Gt => Self::Le,
+            Self::Cc => Self::Cs,
+            Self::Cs => Self::Cc,
+            Self::Vc => Self::Vs,
+            Self::Vs => Self::Vc,
+            Self::Pl => Self::Mi,
+            Self::Mi => Self::Pl,
+        }
+    }
+}
+
+impl Display for Cond {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_lowercase())
+    }
+}
+
+impl FromStr for Cond {
+    type Err = InstructionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "" | "al" => Self::Al,
+            "nv" => Self::Nv,
+            "eq" => Self::Eq,
+            "ne" => Self::Ne,
+            "lt" => Self::Lt,
+            "le" => Self::Le,
+            "ge" => Self::Ge,
+            "gt" => Self::Gt,
+            "cc" => Self::Cc,
+            "cs" => Self::Cs,
+            "vc" => Self::Vc,
+            "vs" => Self::Vs,
+            "pl" => Self::Pl,
+            "mi" => Self::Mi,
+            _ => return Err(InstructionError::BadInstruction),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op2 {
+    Reg(uarch),
+    Imm(uarch),
+}
+
+impl FromStr for Op2 {
+    type Err = LexemeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.chars().next().ok_or(LexemeError::EmptyToken)? {
+            '0' => Op2::Imm(lex::parse_imm(s)?),
+            _ => Op2::Reg(lex::parse_reg(s)?),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum InstructionError {
+    EmptyStr,
+    MissingOps,
+    ExtraOps,
+    BadInstruction,
+    ExpectedSep,
+    InvalidOp,
+    UnknownInstruction,
+    Unencodable,
+    IllegalInstruction,
+}
+
+impl Display for InstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::EmptyStr => "Could not parse instruction from empty string",
+                Self::MissingOps => "Missing operands",
+                Self::ExtraOps => "Extra operands",
+                Self::BadInstruction => "Bad instruction name",
+                Self::ExpectedSep => "Expected separator between operands",
+                Self::InvalidOp => "Invalid operand",
+                Self::UnknownInstruction => "Unknown instruction",
+                Self::Unencodable => "Instruction not supported by encoding",
+                Self::IllegalInstruction => "Illegal instruction",
+            }
+        )
+    }
+}
+
+impl Error for InstructionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        let instr = Instruction::try_from(0x7a02).unwrap();
+        assert_eq!(format!("{}", instr), "mov r10, r2");
+        assert_eq!(format!("{:#}", instr), "mov g6, a2");
+        assert_eq!(
+            "mov g6, a2".parse::<Instruction>().unwrap().to_string(),
+            "mov r10, r2"
+        );
+    }
+
+    #[test]
+    fn conds() {
+        for word in 0b0000..=0b1101 {
+            let cond = Iff::try_from(0x0e00 | word).unwrap().cond;
+            assert_eq!(cond.to_string().parse::<Cond>().unwrap(), cond);
+            assert_eq!(cond.invert().invert(), cond);
+            assert_ne!(cond.invert(), cond);
+        }
+        assert_eq!("lt".parse::<Cond>().unwrap().invert(), Cond::Ge);
+    }
+}
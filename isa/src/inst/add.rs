@@ -3,37 +3,41 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
+use super::{InstructionError, Op2};
 use crate::{lex, uarch};
 
 #[derive(Debug)]
 pub struct Add {
-    op1: uarch,
-    op2: Op2,
+    pub op1: uarch,
+    pub op2: Op2,
 }
 
 impl Display for Add {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = "add";
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for Add {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b1100);
-        Self {
+impl TryFrom<uarch> for Add {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b1100 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
                 false => Op2::Imm(word & 0x007f),
             },
-        }
+        })
     }
 }
 
@@ -67,19 +71,19 @@ impl FromStr for Add {
         }?;
         // Check instruction is correct
         (tokens[0] == "add")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::BadInstruction)?;
         // Parse op1
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
@@ -91,8 +95,6 @@ impl FromStr for Add {
     }
 }
 
-impl Instruction for Add {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -100,7 +102,7 @@ mod tests {
     #[test]
     fn sweep() {
         for mut word in 0xc000..=0xcfff {
-            let instr = Add::from(word);
+            let instr = Add::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xff8f;
             }
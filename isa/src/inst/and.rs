@@ -3,44 +3,48 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
-use crate::{lex, uarch, util};
+use super::{InstructionError, Op2};
+use crate::{iarch, lex, uarch, util};
 
 #[derive(Debug)]
 pub struct And {
-    op1: uarch,
-    op2: Op2,
+    pub op1: uarch,
+    pub op2: Op2,
 }
 
 impl Display for And {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = "and";
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for And {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b0110);
-        Self {
+impl TryFrom<uarch> for And {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b1110 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
                 false => Op2::Imm(util::sign_extend::<7, { uarch::BITS }>(word & 0x007f)),
             },
-        }
+        })
     }
 }
 
 impl From<And> for uarch {
     fn from(instr: And) -> Self {
         let mut word: uarch = 0;
-        word |= 0b0110 << 12;
+        word |= 0b1110 << 12;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
             Op2::Reg(op2) => op2,
@@ -67,23 +71,23 @@ impl FromStr for And {
         }?;
         // Check instruction is correct
         (tokens[0] == "and")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::BadInstruction)?;
         // Parse op1
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
-            Op2::Imm(imm) if imm < 0x80 => Ok(()),
+            Op2::Imm(imm) if (-0x40..0x80).contains(&(imm as iarch)) => Ok(()),
             _ => Err(InstructionError::InvalidOp),
         }?;
         // Create Self from parts
@@ -91,16 +95,14 @@ impl FromStr for And {
     }
 }
 
-impl Instruction for And {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0x6000..=0x6fff {
-            let instr = And::from(word);
+        for mut word in 0xe000..=0xefff {
+            let instr = And::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xff8f;
             }
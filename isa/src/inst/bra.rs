@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::{legacy, Cond, InstructionError, Op2};
+use crate::{iarch, lex, uarch, util, WORDSIZE};
+
+/// Conditions a branch may carry, in order of their legacy encoding.
+const CONDS: [Cond; 7] = [
+    Cond::Al,
+    Cond::Eq,
+    Cond::Ne,
+    Cond::Lt,
+    Cond::Le,
+    Cond::Ge,
+    Cond::Gt,
+];
+
+#[derive(Debug)]
+pub struct Bra {
+    pub op2: Op2,
+    pub link: bool,
+    pub cond: Cond,
+}
+
+impl Display for Bra {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self.link {
+            true => "call",
+            false => "goto",
+        };
+        let cond = match self.cond {
+            Cond::Al => String::new(),
+            cond => cond.to_string(),
+        };
+        let op2 = match self.op2 {
+            Op2::Reg(op2) => format!("&{}", lex::reg_name(op2, f.alternate())),
+            Op2::Imm(imm) => format!("{:#06x}", imm),
+        };
+        write!(f, "{}{} {}", label, cond, op2)
+    }
+}
+
+impl Bra {
+    pub fn from_legacy(word: uarch) -> Result<Self, InstructionError> {
+        if (word >> 12) != 0b1111 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
+            cond: *CONDS
+                .get(((word & 0x0700) >> 8) as usize)
+                .ok_or(InstructionError::IllegalInstruction)?,
+            ..Self::try_from(legacy::remap(word))?
+        })
+    }
+
+    pub fn into_legacy(self) -> Result<uarch, InstructionError> {
+        let cond = CONDS
+            .iter()
+            .position(|&cond| cond == self.cond)
+            .ok_or(InstructionError::Unencodable)? as uarch;
+        Ok(legacy::encode(self.into()) | ((cond << 8) & 0x0700))
+    }
+}
+
+impl TryFrom<uarch> for Bra {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 11) != 0b00000 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
+            op2: match (word & 0x0080) == 0 {
+                true => Op2::Reg(word & 0x000f),
+                false => Op2::Imm(util::sign_extend::<8, { uarch::BITS }>(
+                    (WORDSIZE as uarch) * (word & 0x007f),
+                )),
+            },
+            link: (word & 0x0400) != 0,
+            cond: Cond::Al,
+        })
+    }
+}
+
+impl From<Bra> for uarch {
+    fn from(instr: Bra) -> Self {
+        let mut word: uarch = 0;
+        word |= 0b00000 << 11;
+        word |= ((instr.link as uarch) << 10) & 0x0400;
+        word |= match instr.op2 {
+            Op2::Reg(op2) => op2,
+            Op2::Imm(imm) => 0x0080 | (imm / (WORDSIZE as uarch)),
+        } & 0x00ff;
+        word
+    }
+}
+
+impl FromStr for Bra {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only operate on lowercase strings
+        // (also creates an owned String from &str)
+        let s = s.to_lowercase();
+        // Split into constituent tokens
+        let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
+        // Parse link
+        let (link, cond) = match tokens[0].split_at_checked(4) {
+            Some(("goto", cond)) => (false, cond),
+            Some(("call", cond)) => (true, cond),
+            _ => return Err(InstructionError::BadInstruction.into()),
+        };
+        // Parse cond
+        let cond = cond.parse()?;
+        if !CONDS.contains(&cond) {
+            return Err(InstructionError::BadInstruction.into());
+        }
+        // Parse op2, with registers holding an address
+        let op2 = match &tokens[1..] {
+            [] => return Err(InstructionError::MissingOps.into()),
+            [amp, reg] if amp == "&" => Op2::Reg(lex::parse_reg(reg)?),
+            [imm] => Op2::Imm(lex::parse_imm(imm)?),
+            _ => return Err(InstructionError::ExtraOps.into()),
+        };
+        // Ensure validity of ops
+        match op2 {
+            Op2::Reg(reg) if reg < 0x10 => Ok(()),
+            Op2::Imm(imm)
+                if (-0x80..0x80).contains(&(imm as iarch))
+                    && (imm as usize).is_multiple_of(WORDSIZE) =>
+            {
+                Ok(())
+            }
+            _ => Err(InstructionError::InvalidOp),
+        }?;
+        // Create Self from parts
+        Ok(Self { op2, link, cond })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep() {
+        for mut word in 0x0000..=0x07ff {
+            let instr = Bra::try_from(word).unwrap();
+            word &= 0xfcff;
+            if let Op2::Reg(_) = instr.op2 {
+                word &= 0xff8f;
+            }
+            let decoded: uarch = instr.into();
+            assert_eq!(decoded, word);
+        }
+    }
+
+    #[test]
+    fn syntax() {
+        // Displayed branches parse back to themselves
+        let huffman = (0x0000..=0x07ff).map(Bra::try_from);
+        let legacy = (0xf000..=0xf6ff).map(Bra::from_legacy);
+        for instr in huffman.chain(legacy) {
+            let instr = instr.unwrap();
+            let parsed: Bra = instr.to_string().parse().unwrap();
+            assert_eq!(parsed.op2, instr.op2);
+            assert_eq!(parsed.link, instr.link);
+            assert_eq!(parsed.cond, instr.cond);
+        }
+        // Spec forms are accepted
+        let instr: Bra = "CALL &LR".parse().unwrap();
+        assert_eq!(
+            (instr.op2, instr.link, instr.cond),
+            (Op2::Reg(14), true, Cond::Al)
+        );
+        let instr: Bra = "gotone 0xfffc".parse().unwrap();
+        assert_eq!(instr.to_string(), "gotone 0xfffc");
+        assert_eq!(instr.cond, Cond::Ne);
+        // Malformed branches are rejected
+        assert!("goto".parse::<Bra>().is_err());
+        assert!("goto r1".parse::<Bra>().is_err());
+        assert!("goto 0x1".parse::<Bra>().is_err());
+        assert!("gotonv 0x2".parse::<Bra>().is_err());
+        assert!("b 0x2".parse::<Bra>().is_err());
+        // Other opcodes are rejected
+        assert!(Bra::try_from(0x0800).is_err());
+        assert!(Bra::from_legacy(0x0000).is_err());
+    }
+}
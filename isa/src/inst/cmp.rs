@@ -3,11 +3,11 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
-use crate::{lex, uarch, util};
+use super::{InstructionError, Op2};
+use crate::{iarch, lex, uarch, util};
 
-#[derive(Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Cmp = 0b00,
     Cmn = 0b01,
     Tst = 0b10,
@@ -16,27 +16,31 @@ enum Mode {
 
 #[derive(Debug)]
 pub struct Cmp {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Cmp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = format!("{:?}", self.mode).to_lowercase();
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for Cmp {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 14), 0b00);
-        Self {
+impl TryFrom<uarch> for Cmp {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 14) != 0b10 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -47,16 +51,16 @@ impl From<uarch> for Cmp {
                 0b01 => Mode::Cmn,
                 0b10 => Mode::Tst,
                 0b11 => Mode::Teq,
-                _ => panic!(),
+                _ => return Err(InstructionError::IllegalInstruction),
             },
-        }
+        })
     }
 }
 
 impl From<Cmp> for uarch {
     fn from(instr: Cmp) -> Self {
         let mut word: uarch = 0;
-        word |= 0b00 << 14;
+        word |= 0b10 << 14;
         word |= ((instr.mode as uarch) << 12) & 0x3000;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
@@ -94,17 +98,17 @@ impl FromStr for Cmp {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
-            Op2::Imm(imm) if imm < 0x80 => Ok(()),
+            Op2::Imm(imm) if (-0x40..0x80).contains(&(imm as iarch)) => Ok(()),
             _ => Err(InstructionError::InvalidOp),
         }?;
         // Create Self from parts
@@ -112,16 +116,14 @@ impl FromStr for Cmp {
     }
 }
 
-impl Instruction for Cmp {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0x0000..=0x3fff {
-            let instr = Cmp::from(word);
+        for mut word in 0x8000..=0xbfff {
+            let instr = Cmp::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xff8f;
             }
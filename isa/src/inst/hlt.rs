@@ -0,0 +1,75 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::InstructionError;
+use crate::{lex, uarch};
+
+#[derive(Debug)]
+pub struct Hlt;
+
+impl Display for Hlt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = "hlt";
+        write!(f, "{}", label)
+    }
+}
+
+impl TryFrom<uarch> for Hlt {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 9) != 0b0000110 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self)
+    }
+}
+
+impl From<Hlt> for uarch {
+    fn from(_: Hlt) -> Self {
+        let mut word: uarch = 0;
+        word |= 0b0000110 << 9;
+        word
+    }
+}
+
+impl FromStr for Hlt {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only operate on lowercase strings
+        // (also creates an owned String from &str)
+        let s = s.to_lowercase();
+        // Split into constituent tokens
+        let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
+        // Ensure correct number of tokens
+        match tokens.len().cmp(&1) {
+            Ordering::Less => Err(InstructionError::MissingOps),
+            Ordering::Equal => Ok(()),
+            Ordering::Greater => Err(InstructionError::ExtraOps),
+        }?;
+        // Check instruction is correct
+        (tokens[0] == "hlt")
+            .then_some(())
+            .ok_or(InstructionError::BadInstruction)?;
+        // Create Self from parts
+        Ok(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep() {
+        for mut word in 0x0c00..=0x0dff {
+            let instr = Hlt::try_from(word).unwrap();
+            word &= 0xfe00;
+            let decoded: uarch = instr.into();
+            assert_eq!(decoded, word);
+        }
+    }
+}
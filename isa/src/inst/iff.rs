@@ -0,0 +1,100 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::{Cond, InstructionError};
+use crate::{lex, uarch};
+
+#[derive(Debug)]
+pub struct Iff {
+    pub cond: Cond,
+}
+
+impl Display for Iff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "if{}", self.cond)
+    }
+}
+
+impl TryFrom<uarch> for Iff {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 9) != 0b0000111 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
+            cond: match word & 0x000f {
+                0b0000 => Cond::Al,
+                0b0001 => Cond::Nv,
+                0b0010 => Cond::Eq,
+                0b0011 => Cond::Ne,
+                0b0100 => Cond::Lt,
+                0b0101 => Cond::Le,
+                0b0110 => Cond::Ge,
+                0b0111 => Cond::Gt,
+                0b1000 => Cond::Cc,
+                0b1001 => Cond::Cs,
+                0b1010 => Cond::Vc,
+                0b1011 => Cond::Vs,
+                0b1100 => Cond::Pl,
+                0b1101 => Cond::Mi,
+                _ => return Err(InstructionError::IllegalInstruction),
+            },
+        })
+    }
+}
+
+impl From<Iff> for uarch {
+    fn from(instr: Iff) -> Self {
+        let mut word: uarch = 0;
+        word |= 0b0000111 << 9;
+        word |= (instr.cond as uarch) & 0x000f;
+        word
+    }
+}
+
+impl FromStr for Iff {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only operate on lowercase strings
+        // (also creates an owned String from &str)
+        let s = s.to_lowercase();
+        // Split into constituent tokens
+        let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
+        // Ensure correct number of tokens
+        match tokens.len().cmp(&1) {
+            Ordering::Less => Err(InstructionError::MissingOps),
+            Ordering::Equal => Ok(()),
+            Ordering::Greater => Err(InstructionError::ExtraOps),
+        }?;
+        // Parse cond
+        let cond = match tokens[0].strip_prefix("if") {
+            Some(cond) => cond.parse()?,
+            None => return Err(InstructionError::BadInstruction.into()),
+        };
+        // Create Self from parts
+        Ok(Self { cond })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep() {
+        for mut word in 0x0e00..=0x0fff {
+            if (word & 0x000f) >= 0b1110 {
+                assert!(Iff::try_from(word).is_err());
+                continue;
+            }
+            let instr = Iff::try_from(word).unwrap();
+            word &= 0xfe0f;
+            let decoded: uarch = instr.into();
+            assert_eq!(decoded, word);
+        }
+    }
+}
@@ -3,20 +3,20 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
+use super::{InstructionError, Op2};
 use crate::{iarch, lex, uarch, util, WORDSIZE};
 
-#[derive(Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Ldr = 0b0,
     Pop = 0b1,
 }
 
 #[derive(Debug)]
 pub struct Ldr {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Ldr {
@@ -24,25 +24,29 @@ impl Display for Ldr {
         let label = format!("{:?}", self.mode).to_lowercase();
         match self.mode {
             Mode::Ldr => {
-                let op1 = format!("r{}", self.op1);
+                let op1 = lex::reg_name(self.op1, f.alternate());
                 let op2 = match self.op2 {
-                    Op2::Reg(op2) => format!("r{}", op2),
+                    Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
                     Op2::Imm(imm) => format!("{:+#07x}", imm),
                 };
                 write!(f, "{} {}, *{}", label, op1, op2)
             }
             Mode::Pop => {
-                let op1 = format!("r{}", self.op1);
+                let op1 = lex::reg_name(self.op1, f.alternate());
                 write!(f, "{} {}", label, op1)
             }
         }
     }
 }
 
-impl From<uarch> for Ldr {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b1011);
-        Self {
+impl TryFrom<uarch> for Ldr {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b0011 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -54,14 +58,14 @@ impl From<uarch> for Ldr {
                 false => Mode::Ldr,
                 true => Mode::Pop,
             },
-        }
+        })
     }
 }
 
 impl From<Ldr> for uarch {
     fn from(instr: Ldr) -> Self {
         let mut word: uarch = 0;
-        word |= 0b1011 << 12;
+        word |= 0b0011 << 12;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
             Op2::Reg(op2) => match instr.mode {
@@ -85,7 +89,7 @@ impl FromStr for Ldr {
         let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
         // Ensure at least one token
         (!tokens.is_empty())
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::MissingOps)?;
         // Parse mode
         let mode = match &*tokens[0] {
@@ -107,21 +111,24 @@ impl FromStr for Ldr {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Ensure validity of op1
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         // Parse for Mode::Ldr
         let op2 = match mode {
             Mode::Ldr => {
                 // Look for "," separator
                 (tokens[2] == ",")
-                    .then(|| ())
+                    .then_some(())
                     .ok_or(InstructionError::ExpectedSep)?;
                 // Parse op2
                 let op2 = tokens[3].parse()?;
                 // Ensure validity of op2
                 match op2 {
                     Op2::Reg(reg) if reg < 0x10 => Ok(()),
-                    Op2::Imm(imm) if (imm as iarch) < 0x80 && (imm as usize % WORDSIZE == 0) => {
+                    Op2::Imm(imm)
+                        if (-0x80..0x80).contains(&(imm as iarch))
+                            && (imm as usize).is_multiple_of(WORDSIZE) =>
+                    {
                         Ok(())
                     }
                     _ => Err(InstructionError::InvalidOp),
@@ -135,16 +142,14 @@ impl FromStr for Ldr {
     }
 }
 
-impl Instruction for Ldr {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0xb000..=0xbfff {
-            let instr = Ldr::from(word);
+        for mut word in 0x3000..=0x3fff {
+            let instr = Ldr::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xffcf;
             }
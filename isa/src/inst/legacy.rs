@@ -0,0 +1,94 @@
+//! Support for the legacy fixed-width opcode layout.
+//!
+//! Apart from their opcodes, most instructions share the same layout in both
+//! encodings, so words are rewritten between their legacy and Huffman
+//! counterparts.
+
+use super::bra::Bra;
+use super::{Instruction, InstructionError};
+use crate::uarch;
+
+pub fn decode(word: uarch) -> Result<Instruction, InstructionError> {
+    match Instruction::try_from(remap(word))? {
+        // Branches are the only instruction with a legacy-only field
+        Instruction::Bra(_) => Ok(Instruction::Bra(Bra::from_legacy(word)?)),
+        instr => Ok(instr),
+    }
+}
+
+pub fn remap(word: uarch) -> uarch {
+    let args = word & 0x0fff;
+    match word >> 12 {
+        0b0000..=0b0011 => 0x8000 | word, // 0x0..=0x3 => CMP
+        0b0100 => 0xd000 | args,          // 0x4       => ORR
+        0b0101 => 0x1000 | args,          // 0x5       => XOR
+        0b0110 => 0xe000 | args,          // 0x6       => AND
+        0b0111 => 0x6000 | args,          // 0x7       => MUL
+        0b1000..=0b1001 => word ^ 0xc000, // 0x8..=0x9 => SUB
+        0b1010 => 0x7000 | args,          // 0xa       => MOV
+        0b1011 => 0x3000 | args,          // 0xb       => LDR
+        0b1100 => 0xc000 | args,          // 0xc       => ADD
+        0b1101 => 0x2000 | args,          // 0xd       => STR
+        0b1110 => 0xf000 | args,          // 0xe       => SHF
+        _ => match word >> 4 {
+            0x0f70 => 0x0c00,                              // 0xf70     => HLT
+            0x0f71 => 0x0e00 | (word & 0x000f),            // 0xf71     => IFF
+            0x0f72 => 0x0800 | (word & 0x0003),            // 0xf72     => SYS
+            _ => ((word & 0x0800) >> 1) | (word & 0x00ff), // 0xf       => BRA
+        },
+    }
+}
+
+pub fn encode(word: uarch) -> uarch {
+    let args = word & 0x0fff;
+    match word {
+        0x0000..=0x07ff => 0xf000 | ((word & 0x0400) << 1) | (word & 0x00ff), // BRA => 0xf
+        0x0800..=0x0bff => 0xf720 | (word & 0x0003),                          // SYS => 0xf72
+        0x0c00..=0x0dff => 0xf700,                                            // HLT => 0xf70
+        0x0e00..=0x0fff => 0xf710 | (word & 0x000f),                          // IFF => 0xf71
+        0x1000..=0x1fff => 0x5000 | args,                                     // XOR => 0x5
+        0x2000..=0x2fff => 0xd000 | args,                                     // STR => 0xd
+        0x3000..=0x3fff => 0xb000 | args,                                     // LDR => 0xb
+        0x4000..=0x5fff => word ^ 0xc000,                                     // SUB => 0x8..=0x9
+        0x6000..=0x6fff => 0x7000 | args,                                     // MUL => 0x7
+        0x7000..=0x7fff => 0xa000 | args,                                     // MOV => 0xa
+        0x8000..=0xbfff => word & 0x3fff,                                     // CMP => 0x0..=0x3
+        0xc000..=0xcfff => 0xc000 | args,                                     // ADD => 0xc
+        0xd000..=0xdfff => 0x4000 | args,                                     // ORR => 0x4
+        0xe000..=0xefff => 0x6000 | args,                                     // AND => 0x6
+        0xf000..=0xffff => 0xe000 | args,                                     // SHF => 0xe
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remap() {
+        for (word, legacy, text) in [
+            (0x0482, 0xf882, "call 0x0004"),
+            (0x0800, 0xf720, "sys"),
+            (0x0801, 0xf721, "rti"),
+            (0x0c00, 0xf700, "hlt"),
+            (0x0e02, 0xf712, "ifeq"),
+            (0x5281, 0x9281, "rsb r2, 0x0001"),
+            (0x7aaa, 0xaaaa, "mov r10, 0x002a"),
+            (0x8142, 0x0142, "cmp r1, r2"),
+            (0xf0c3, 0xe0c3, "lsl r0, 0x0003"),
+        ] {
+            assert_eq!(encode(word), legacy);
+            assert_eq!(super::remap(legacy), word);
+            assert_eq!(decode(legacy).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn cond() {
+        let instr = decode(0xf102).unwrap();
+        assert_eq!(instr.to_string(), "gotoeq &r2");
+        assert_eq!(instr.encode(crate::Encoding::Legacy).unwrap(), 0xf102);
+        // Condition 0b111 is unused
+        assert!(decode(0xf782).is_err());
+    }
+}
@@ -3,11 +3,11 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
-use crate::{lex, uarch, util};
+use super::{InstructionError, Op2};
+use crate::{iarch, lex, uarch, util};
 
-#[derive(Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Mov = 0b00,
     Neg = 0b01,
     Not = 0b10,
@@ -15,27 +15,31 @@ enum Mode {
 
 #[derive(Debug)]
 pub struct Mov {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Mov {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = format!("{:?}", self.mode).to_lowercase();
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for Mov {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b1010);
-        Self {
+impl TryFrom<uarch> for Mov {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b0111 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -47,17 +51,17 @@ impl From<uarch> for Mov {
                     0b00 => Mode::Mov,
                     0b01 => Mode::Neg,
                     0b10 => Mode::Not,
-                    _ => panic!(),
+                    _ => return Err(InstructionError::IllegalInstruction),
                 },
             },
-        }
+        })
     }
 }
 
 impl From<Mov> for uarch {
     fn from(instr: Mov) -> Self {
         let mut word: uarch = 0;
-        word |= 0b1010 << 12;
+        word |= 0b0111 << 12;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
             Op2::Reg(op2) => ((instr.mode as uarch) << 4) | op2,
@@ -93,17 +97,17 @@ impl FromStr for Mov {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
-            Op2::Imm(imm) if imm < 0x80 => Ok(()),
+            Op2::Imm(imm) if (-0x40..0x80).contains(&(imm as iarch)) => Ok(()),
             _ => Err(InstructionError::InvalidOp),
         }?;
         // Create Self from parts
@@ -111,20 +115,18 @@ impl FromStr for Mov {
     }
 }
 
-impl Instruction for Mov {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0xa000..=0xafff {
-            match (word & 0x00b0) >> 4 {
-                0b0011 => continue,
-                _ => (),
+        for mut word in 0x7000..=0x7fff {
+            if (word & 0x00b0) >> 4 == 0b0011 {
+                assert!(Mov::try_from(word).is_err());
+                continue;
             }
-            let instr = Mov::from(word);
+            let instr = Mov::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xffbf;
             }
@@ -3,11 +3,11 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
+use super::{InstructionError, Op2};
 use crate::{lex, uarch};
 
-#[derive(Clone, Copy, Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Lsr = 0b000,
     Asr = 0b001,
     Ror = 0b010,
@@ -18,27 +18,31 @@ enum Mode {
 
 #[derive(Debug)]
 pub struct Shf {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Shf {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = format!("{:?}", self.mode).to_lowercase();
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for Shf {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b1110);
-        Self {
+impl TryFrom<uarch> for Shf {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b1111 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -51,16 +55,16 @@ impl From<uarch> for Shf {
                 0b100 => Mode::Lsl,
                 0b101 => Mode::Asl,
                 0b110 => Mode::Rol,
-                _ => panic!(),
+                _ => return Err(InstructionError::IllegalInstruction),
             },
-        }
+        })
     }
 }
 
 impl From<Shf> for uarch {
     fn from(instr: Shf) -> Self {
         let mut word: uarch = 0;
-        word |= 0b1110 << 12;
+        word |= 0b1111 << 12;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= ((instr.mode as uarch) << 4) & 0x0070;
         word |= match instr.op2 {
@@ -100,13 +104,13 @@ impl FromStr for Shf {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
@@ -118,20 +122,18 @@ impl FromStr for Shf {
     }
 }
 
-impl Instruction for Shf {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for word in 0xe000..=0xefff {
-            match (word & 0x0030) >> 4 {
-                0b11 => continue,
-                _ => (),
+        for word in 0xf000..=0xffff {
+            if (word & 0x0030) >> 4 == 0b11 {
+                assert!(Shf::try_from(word).is_err());
+                continue;
             }
-            let instr = Shf::from(word);
+            let instr = Shf::try_from(word).unwrap();
             let decoded: uarch = instr.into();
             assert_eq!(decoded, word);
         }
@@ -3,20 +3,20 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
+use super::{InstructionError, Op2};
 use crate::{iarch, lex, uarch, util, WORDSIZE};
 
-#[derive(Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Str = 0b0,
     Push = 0b1,
 }
 
 #[derive(Debug)]
 pub struct Str {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Str {
@@ -24,25 +24,29 @@ impl Display for Str {
         let label = format!("{:?}", self.mode).to_lowercase();
         match self.mode {
             Mode::Str => {
-                let op1 = format!("r{}", self.op1);
+                let op1 = lex::reg_name(self.op1, f.alternate());
                 let op2 = match self.op2 {
-                    Op2::Reg(op2) => format!("r{}", op2),
+                    Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
                     Op2::Imm(imm) => format!("{:+#07x}", imm),
                 };
                 write!(f, "{} {}, &{}", label, op1, op2)
             }
             Mode::Push => {
-                let op1 = format!("r{}", self.op1);
+                let op1 = lex::reg_name(self.op1, f.alternate());
                 write!(f, "{} {}", label, op1)
             }
         }
     }
 }
 
-impl From<uarch> for Str {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 12), 0b1101);
-        Self {
+impl TryFrom<uarch> for Str {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 12) != 0b0010 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -54,14 +58,14 @@ impl From<uarch> for Str {
                 false => Mode::Str,
                 true => Mode::Push,
             },
-        }
+        })
     }
 }
 
 impl From<Str> for uarch {
     fn from(instr: Str) -> Self {
         let mut word: uarch = 0;
-        word |= 0b1101 << 12;
+        word |= 0b0010 << 12;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
             Op2::Reg(op2) => match instr.mode {
@@ -85,7 +89,7 @@ impl FromStr for Str {
         let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
         // Ensure at least one token
         (!tokens.is_empty())
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::MissingOps)?;
         // Parse mode
         let mode = match &*tokens[0] {
@@ -107,21 +111,24 @@ impl FromStr for Str {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Ensure validity of op1
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         // Parse for Mode::Str
         let op2 = match mode {
             Mode::Str => {
                 // Look for "," separator
                 (tokens[2] == ",")
-                    .then(|| ())
+                    .then_some(())
                     .ok_or(InstructionError::ExpectedSep)?;
                 // Parse op2
                 let op2 = tokens[3].parse()?;
                 // Ensure validity of op2
                 match op2 {
                     Op2::Reg(reg) if reg < 0x10 => Ok(()),
-                    Op2::Imm(imm) if (imm as iarch) < 0x80 && (imm as usize % WORDSIZE == 0) => {
+                    Op2::Imm(imm)
+                        if (-0x80..0x80).contains(&(imm as iarch))
+                            && (imm as usize).is_multiple_of(WORDSIZE) =>
+                    {
                         Ok(())
                     }
                     _ => Err(InstructionError::InvalidOp),
@@ -135,16 +142,14 @@ impl FromStr for Str {
     }
 }
 
-impl Instruction for Str {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0xd000..=0xdfff {
-            let instr = Str::from(word);
+        for mut word in 0x2000..=0x2fff {
+            let instr = Str::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xffcf;
             }
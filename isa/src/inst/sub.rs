@@ -3,38 +3,42 @@ use std::error::Error;
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use super::{Instruction, InstructionError, Op2};
+use super::{InstructionError, Op2};
 use crate::{lex, uarch};
 
-#[derive(Debug)]
-enum Mode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
     Sub = 0b0,
     Rsb = 0b1,
 }
 
 #[derive(Debug)]
 pub struct Sub {
-    op1: uarch,
-    op2: Op2,
-    mode: Mode,
+    pub op1: uarch,
+    pub op2: Op2,
+    pub mode: Mode,
 }
 
 impl Display for Sub {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let label = format!("{:?}", self.mode).to_lowercase();
-        let op1 = format!("r{}", self.op1);
+        let op1 = lex::reg_name(self.op1, f.alternate());
         let op2 = match self.op2 {
-            Op2::Reg(op2) => format!("r{}", op2),
+            Op2::Reg(op2) => lex::reg_name(op2, f.alternate()),
             Op2::Imm(imm) => format!("{:#06x}", imm),
         };
         write!(f, "{} {}, {}", label, op1, op2)
     }
 }
 
-impl From<uarch> for Sub {
-    fn from(word: uarch) -> Self {
-        assert_eq!((word >> 13), 0b100);
-        Self {
+impl TryFrom<uarch> for Sub {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 13) != 0b010 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
             op1: (word & 0x0f00) >> 8,
             op2: match (word & 0x0080) == 0 {
                 true => Op2::Reg(word & 0x000f),
@@ -43,16 +47,16 @@ impl From<uarch> for Sub {
             mode: match (word & 0x1000) >> 12 {
                 0b0 => Mode::Sub,
                 0b1 => Mode::Rsb,
-                _ => panic!(),
+                _ => return Err(InstructionError::IllegalInstruction),
             },
-        }
+        })
     }
 }
 
 impl From<Sub> for uarch {
     fn from(instr: Sub) -> Self {
         let mut word: uarch = 0;
-        word |= 0b100 << 13;
+        word |= 0b010 << 13;
         word |= ((instr.mode as uarch) << 12) & 0x1000;
         word |= (instr.op1 << 8) & 0x0f00;
         word |= match instr.op2 {
@@ -88,13 +92,13 @@ impl FromStr for Sub {
         let op1 = lex::parse_reg(&tokens[1])?;
         // Look for "," separator
         (tokens[2] == ",")
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::ExpectedSep)?;
         // Parse op2
         let op2 = tokens[3].parse()?;
         // Ensure validity of ops
         (op1 < 0x10)
-            .then(|| ())
+            .then_some(())
             .ok_or(InstructionError::InvalidOp)?;
         match op2 {
             Op2::Reg(reg) if reg < 0x10 => Ok(()),
@@ -106,16 +110,14 @@ impl FromStr for Sub {
     }
 }
 
-impl Instruction for Sub {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sweep() {
-        for mut word in 0x8000..=0x9fff {
-            let instr = Sub::from(word);
+        for mut word in 0x4000..=0x5fff {
+            let instr = Sub::try_from(word).unwrap();
             if let Op2::Reg(_) = instr.op2 {
                 word &= 0xff8f;
             }
@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::InstructionError;
+use crate::{lex, uarch};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Sys = 0b00,
+    Rti = 0b01,
+    Sei = 0b10,
+    Cli = 0b11,
+}
+
+#[derive(Debug)]
+pub struct Sys {
+    pub mode: Mode,
+}
+
+impl Display for Sys {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = format!("{:?}", self.mode).to_lowercase();
+        write!(f, "{}", label)
+    }
+}
+
+impl TryFrom<uarch> for Sys {
+    type Error = InstructionError;
+
+    fn try_from(word: uarch) -> Result<Self, Self::Error> {
+        if (word >> 10) != 0b000010 {
+            return Err(InstructionError::IllegalInstruction);
+        }
+        Ok(Self {
+            mode: match word & 0x0003 {
+                0b00 => Mode::Sys,
+                0b01 => Mode::Rti,
+                0b10 => Mode::Sei,
+                0b11 => Mode::Cli,
+                _ => return Err(InstructionError::IllegalInstruction),
+            },
+        })
+    }
+}
+
+impl From<Sys> for uarch {
+    fn from(instr: Sys) -> Self {
+        let mut word: uarch = 0;
+        word |= 0b000010 << 10;
+        word |= (instr.mode as uarch) & 0x0003;
+        word
+    }
+}
+
+impl FromStr for Sys {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Only operate on lowercase strings
+        // (also creates an owned String from &str)
+        let s = s.to_lowercase();
+        // Split into constituent tokens
+        let tokens = lex::tokenize(&s).ok_or(InstructionError::EmptyStr)?;
+        // Ensure correct number of tokens
+        match tokens.len().cmp(&1) {
+            Ordering::Less => Err(InstructionError::MissingOps),
+            Ordering::Equal => Ok(()),
+            Ordering::Greater => Err(InstructionError::ExtraOps),
+        }?;
+        // Parse mode
+        let mode = match &*tokens[0] {
+            "sys" => Mode::Sys,
+            "rti" => Mode::Rti,
+            "sei" => Mode::Sei,
+            "cli" => Mode::Cli,
+            _ => return Err(InstructionError::BadInstruction.into()),
+        };
+        // Create Self from parts
+        Ok(Self { mode })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep() {
+        for mut word in 0x0800..=0x0bff {
+            let instr = Sys::try_from(word).unwrap();
+            word &= 0xfc03;
+            let decoded: uarch = instr.into();
+            assert_eq!(decoded, word);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::result;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::uarch;
+
+type Result<T> = result::Result<T, LexemeError>;
+
+pub fn tokenize(line: &str) -> Option<Vec<String>> {
+    let tokens: Vec<_> = spans(line)
+        .into_iter()
+        .map(|span| line[span].to_string()) // convert from &str -> String
+        .collect();
+    match tokens.len() {
+        0 => None,
+        _ => Some(tokens),
+    }
+}
+
+/// Locates the byte range of each token within a line.
+pub fn spans(line: &str) -> Vec<Range<usize>> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r#"'(\\.|[^'\\])'|"(\\.|[^"\\])*"|\w+|<<|>>|\S"#).unwrap();
+    }
+    RE.find_iter(line) // split into words, literals and symbols
+        .take_while(|m| m.as_str() != ";") // strip comments
+        .map(|m| m.range())
+        .collect()
+}
+
+pub fn parse_reg(token: &str) -> Result<uarch> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(r|a|g)(\d+)$").unwrap();
+    }
+    (|| match token {
+        "sp" => Some(13),
+        "lr" => Some(14),
+        "pc" => Some(15),
+        token => {
+            let captures = RE.captures(token)?;
+            let idx = uarch::from_str_radix(captures.get(2)?.as_str(), 10).ok()?;
+            match captures.get(1)?.as_str() {
+                "a" if idx < 4 => Some(idx),
+                "g" if idx < 9 => Some(idx + 4),
+                "r" => Some(idx),
+                _ => None,
+            }
+        }
+    })()
+    .ok_or_else(|| LexemeError::InvalidReg(token.to_string()))
+}
+
+/// Formats a register by index, or by its ABI name if `abi` is set.
+pub fn reg_name(idx: uarch, abi: bool) -> String {
+    match (idx, abi) {
+        (0x0..=0x3, true) => format!("a{}", idx),
+        (0x4..=0xc, true) => format!("g{}", idx - 4),
+        (0xd, true) => "sp".to_string(),
+        (0xe, true) => "lr".to_string(),
+        (0xf, true) => "pc".to_string(),
+        _ => format!("r{}", idx),
+    }
+}
+
+pub fn parse_imm(token: &str) -> Result<uarch> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^0(b|d|o|x)([[:xdigit:]]+)$").unwrap();
+    }
+    (|| {
+        let captures = RE.captures(token)?;
+        match captures.get(1)?.as_str() {
+            "b" => uarch::from_str_radix(captures.get(2)?.as_str(), 2).ok(),
+            "d" => uarch::from_str_radix(captures.get(2)?.as_str(), 10).ok(),
+            "o" => uarch::from_str_radix(captures.get(2)?.as_str(), 8).ok(),
+            "x" => uarch::from_str_radix(captures.get(2)?.as_str(), 16).ok(),
+            _ => None,
+        }
+    })()
+    .ok_or_else(|| LexemeError::InvalidImm(token.to_string()))
+}
+
+pub fn parse_char(token: &str) -> Result<u8> {
+    (|| {
+        let inner = token.strip_prefix('\'')?.strip_suffix('\'')?;
+        match unescape(inner)?[..] {
+            [byte] => Some(byte),
+            _ => None,
+        }
+    })()
+    .ok_or_else(|| LexemeError::InvalidChar(token.to_string()))
+}
+
+pub fn parse_str(token: &str) -> Result<Vec<u8>> {
+    (|| unescape(token.strip_prefix('"')?.strip_suffix('"')?))()
+        .ok_or_else(|| LexemeError::InvalidStr(token.to_string()))
+}
+
+fn unescape(s: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                '0' => '\0',
+                'n' => '\n',
+                'r' => '\r',
+                't' => '\t',
+                c @ ('\\' | '\'' | '"') => c,
+                _ => return None,
+            },
+            c => c,
+        };
+        // Only ASCII is representable
+        bytes.push(u8::try_from(c).ok().filter(u8::is_ascii)?);
+    }
+    Some(bytes)
+}
+
+#[derive(Debug)]
+pub enum LexemeError {
+    EmptyToken,
+    InvalidReg(String),
+    InvalidImm(String),
+    InvalidChar(String),
+    InvalidStr(String),
+}
+
+impl Display for LexemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::EmptyToken => "Found empty token; expected content".to_string(),
+                Self::InvalidReg(token) => format!("Could not parse register from `{}`", token),
+                Self::InvalidImm(token) => format!("Could not parse immediate from `{}`", token),
+                Self::InvalidChar(token) => format!("Could not parse character from `{}`", token),
+                Self::InvalidStr(token) => format!("Could not parse string from `{}`", token),
+            }
+        )
+    }
+}
+
+impl Error for LexemeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("ldr r0, ((x<<1)+';') ; comment").unwrap(),
+            ["ldr", "r0", ",", "(", "(", "x", "<<", "1", ")", "+", "';'", ")"]
+        );
+        assert_eq!(
+            tokenize(".include \"a b.s\"").unwrap(),
+            [".", "include", "\"a b.s\""]
+        );
+        assert_eq!(parse_reg("a3").unwrap(), 3);
+        assert_eq!(parse_reg("g8").unwrap(), 12);
+        assert!(parse_reg("a4").is_err());
+        assert_eq!(parse_char("'\\n'").unwrap(), b'\n');
+        assert!(parse_char("'ab'").is_err());
+        assert_eq!(parse_str("\"hi\\0\"").unwrap(), b"hi\0");
+    }
+}
@@ -0,0 +1,18 @@
+//! # Instruction Set
+//!
+//! `isa` defines the LANv1 instruction set of the KAP-16 microprocessor.
+
+use std::mem;
+
+pub mod inst;
+pub mod lex;
+mod util;
+
+pub use self::inst::{Cond, Encoding, Instruction, InstructionError, Op2};
+
+#[allow(non_camel_case_types)]
+pub type iarch = i16;
+#[allow(non_camel_case_types)]
+pub type uarch = u16;
+
+pub const WORDSIZE: usize = mem::size_of::<uarch>();
@@ -19,7 +19,7 @@ _divide:
     mov r2, 0d0         ; let rem: r2 = 0
 .repeat 16
 .begin ; long division
-    shr a0, 0d1
+    lsr a0, 0d1
     ifcs
     orr r2, 0b1
     cmp r2, a1